@@ -1,6 +1,13 @@
-use crate::expression::Value;
+use crate::{
+    expression::Value,
+    structs::{Field, Instance, StructDecl},
+    var_type::VarType,
+};
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
 
-pub fn get_line_from_index(lines: &Vec<usize>, index: &usize) -> usize {
+pub fn get_line_from_index(lines: &[usize], index: &usize) -> usize {
     // Find the first newline index greater than the given index
     match lines.binary_search(index) {
         // If the index matches exactly a newline index, return the corresponding line number
@@ -9,21 +16,83 @@ pub fn get_line_from_index(lines: &Vec<usize>, index: &usize) -> usize {
         Err(pos) => pos + 1,
     }
 }
-pub fn get_col(index: &usize, lines: &Vec<usize>) -> usize {
+pub fn get_col(index: &usize, lines: &[usize]) -> usize {
     let l = get_line_from_index(lines, index);
-    if lines.len() <= 0 {
-        return *index;
+    // the first line has no newline before it
+    if l < 2 {
+        return *index + 1;
     }
+    index - lines[l - 2]
+}
+
+/// An error while the script runs, on its way to a `catch`. A `throw` carries its value along.
+pub struct RuntimeError {
+    pub message: String,
+    pub line: usize,
+    pub thrown: Option<Value>,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at line {}", self.message, self.line)
+    }
+}
 
-    if lines[l - 1] > *index {
-        return *index ;
-    } else {
-        return index - lines[l - 1];
+// the parser and the other passes before running report errors as plain text
+impl From<RuntimeError> for String {
+    fn from(err: RuntimeError) -> String {
+        err.to_string()
     }
 }
 
-pub fn error(msg: &str, index: &usize, lines: &Vec<usize>) -> Result<Value, String> {
+pub fn error<T, E: From<RuntimeError>>(msg: &str, index: &usize, lines: &[usize]) -> Result<T, E> {
+    Result::Err(E::from(RuntimeError {
+        message: msg.to_owned(),
+        line: get_line_from_index(lines, index),
+        thrown: None,
+    }))
+}
+
+/// Like `error`, but also gives the column, for mistakes inside a line such as a bad escape.
+pub fn error_at<T>(msg: &str, index: &usize, lines: &[usize]) -> Result<T, String> {
     let l = get_line_from_index(lines, index);
-    // let c = get_col(index, lines);
-    return Result::Err(format!("{} at line {}", msg, l));
+    let c = get_col(index, lines);
+    Result::Err(format!("{} at line {}, column {}", msg, l, c))
+}
+
+/// Fails with `val` so that a surrounding `catch` gets the value itself back.
+pub fn throw<T>(val: Value, index: &usize, lines: &[usize]) -> Result<T, RuntimeError> {
+    Result::Err(RuntimeError {
+        message: format!("Uncaught exception: {}", val),
+        line: get_line_from_index(lines, index),
+        thrown: Some(val),
+    })
+}
+
+/// The value a `catch` binds for an error: the thrown value, or an `Error` describing it.
+pub fn caught(err: RuntimeError) -> Value {
+    if let Some(val) = err.thrown {
+        return val;
+    }
+    Value::Instance(Rc::new(Instance {
+        decl: error_struct(),
+        values: RefCell::new(vec![
+            Value::StringVal(err.message),
+            Value::Int(err.line as i64),
+        ]),
+    }))
+}
+
+/// `struct Error { message: str, line: int }`, what runtime errors are caught as.
+pub fn error_struct() -> Rc<StructDecl> {
+    let field = |name: &str, ftype: VarType| Field {
+        name: name.to_owned(),
+        ftype,
+        index: 0,
+    };
+    Rc::new(StructDecl {
+        name: "Error".to_owned(),
+        fields: vec![field("message", VarType::Str), field("line", VarType::Int)],
+        index: 0,
+    })
 }
@@ -0,0 +1,147 @@
+use crate::{
+    badger_debug::{error, error_struct, RuntimeError},
+    expression::Value, symbol_table::SymbolTable, var_type::VarType,
+};
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+
+pub type NativeFn = fn(&[Value], &usize, &[usize]) -> Result<Value, RuntimeError>;
+
+pub struct NativeFunction {
+    pub name: &'static str,
+    // `None` accepts any number of arguments
+    pub arity: Option<usize>,
+    pub func: NativeFn,
+}
+
+impl NativeFunction {
+    pub fn call(
+        &self,
+        args: Vec<Value>,
+        index: &usize,
+        debug_lines: &[usize],
+    ) -> Result<Value, RuntimeError> {
+        if let Some(arity) = self.arity {
+            if args.len() != arity {
+                return error(
+                    &format!(
+                        "Function '{}' expects {} arguments but got {}",
+                        self.name,
+                        arity,
+                        args.len()
+                    ),
+                    index,
+                    debug_lines,
+                );
+            }
+        }
+        (self.func)(&args, index, debug_lines)
+    }
+}
+
+/// A fresh global scope whose parent holds the built-in functions, so scripts may shadow them.
+pub fn global_table() -> SymbolTable {
+    let mut builtins = SymbolTable::new(None);
+    define(&mut builtins, "print", None, native_print);
+    define(&mut builtins, "println", None, native_println);
+    define(&mut builtins, "eprint", None, native_eprint);
+    define(&mut builtins, "has", Some(2), native_has);
+    define(&mut builtins, "keys", Some(1), native_keys);
+    define(&mut builtins, "values", Some(1), native_values);
+    define(&mut builtins, "remove", Some(2), native_remove);
+    builtins
+        .map
+        .insert("Error".to_owned(), Value::Struct(error_struct()));
+    builtins.types.insert("Error".to_owned(), VarType::Var);
+
+    SymbolTable::new(Some(Rc::new(RefCell::new(builtins))))
+}
+
+fn define(table: &mut SymbolTable, name: &'static str, arity: Option<usize>, func: NativeFn) {
+    let native = NativeFunction { name, arity, func };
+    table
+        .map
+        .insert(name.to_owned(), Value::Native(Rc::new(native)));
+    table.types.insert(name.to_owned(), VarType::Fxn);
+}
+
+fn join(args: &[Value]) -> String {
+    let parts: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    parts.join(" ")
+}
+
+type Entries = Rc<RefCell<Vec<(String, Value)>>>;
+
+fn map_args(
+    name: &str,
+    args: &[Value],
+    index: &usize,
+    lines: &[usize],
+) -> Result<(Entries, Option<String>), RuntimeError> {
+    let entries = match &args[0] {
+        Value::Map(entries) => Rc::clone(entries),
+        other => {
+            return error(
+                &format!("'{}' expects a map but got {}", name, other.type_name()),
+                index,
+                lines,
+            )
+        }
+    };
+    match args.get(1) {
+        None => Ok((entries, None)),
+        Some(Value::StringVal(key)) => Ok((entries, Some(key.clone()))),
+        Some(_) => error("Map keys must be strings", index, lines),
+    }
+}
+
+fn native_has(args: &[Value], index: &usize, lines: &[usize]) -> Result<Value, RuntimeError> {
+    let (entries, key) = map_args("has", args, index, lines)?;
+    let key = key.unwrap_or_default();
+    let found = entries.borrow().iter().any(|(k, _)| *k == key);
+    Ok(Value::Boolean(found))
+}
+
+fn native_keys(args: &[Value], index: &usize, lines: &[usize]) -> Result<Value, RuntimeError> {
+    let (entries, _) = map_args("keys", args, index, lines)?;
+    let keys: Vec<Value> = entries
+        .borrow()
+        .iter()
+        .map(|(k, _)| Value::StringVal(k.clone()))
+        .collect();
+    Ok(Value::Array(Rc::new(RefCell::new(keys))))
+}
+
+fn native_values(args: &[Value], index: &usize, lines: &[usize]) -> Result<Value, RuntimeError> {
+    let (entries, _) = map_args("values", args, index, lines)?;
+    let vals: Vec<Value> = entries.borrow().iter().map(|(_, v)| v.clone()).collect();
+    Ok(Value::Array(Rc::new(RefCell::new(vals))))
+}
+
+/// Removes a key and returns its value, or `null` when the key was not there.
+fn native_remove(args: &[Value], index: &usize, lines: &[usize]) -> Result<Value, RuntimeError> {
+    let (entries, key) = map_args("remove", args, index, lines)?;
+    let key = key.unwrap_or_default();
+    let mut entries = entries.borrow_mut();
+    match entries.iter().position(|(k, _)| *k == key) {
+        Some(at) => Ok(entries.remove(at).1),
+        None => Ok(Value::Null),
+    }
+}
+
+fn native_print(args: &[Value], _index: &usize, _lines: &[usize]) -> Result<Value, RuntimeError> {
+    print!("{}", join(args));
+    let _ = std::io::stdout().flush();
+    Ok(Value::Null)
+}
+
+fn native_println(args: &[Value], _index: &usize, _lines: &[usize]) -> Result<Value, RuntimeError> {
+    println!("{}", join(args));
+    Ok(Value::Null)
+}
+
+fn native_eprint(args: &[Value], _index: &usize, _lines: &[usize]) -> Result<Value, RuntimeError> {
+    eprint!("{}", join(args));
+    Ok(Value::Null)
+}
@@ -1,116 +1,545 @@
-use crate::{encoder::Encoder, statement::Statement};
+use crate::{
+    badger_debug::error,
+    encoder::{literal, Encoder},
+    expression::{Expression, Value},
+    function::FunctionDecl,
+    statement::{Pattern, Statement},
+    tokenizer::TokenType,
+    var_type::VarType,
+};
+use std::collections::{HashMap, HashSet};
 
 pub struct Compiler<'a> {
     pub ir_code: Vec<String>,
     pub source: &'a Vec<Option<Statement>>,
     pub lines: &'a Vec<usize>,
+    fxn_depth: usize,
+    // (`jump` lines to patch for `continue`, `jump` lines to patch for `break`)
+    loops: Vec<(Vec<usize>, Vec<usize>)>,
+    // names declared `int`, so the encoder can pick integer instructions
+    ints: HashSet<String>,
+    // constants with a literal value, which the encoder puts in place of the name
+    consts: HashMap<String, String>,
+    // the `try`s whose body or `catch` is being compiled, innermost last
+    tries: Vec<OpenTry>,
+}
+
+/// A `try` that `break`, `continue` and `return` have to leave through its `finally`.
+struct OpenTry {
+    id: usize,
+    // how many loops were open at the `try`; jumps to those leave it
+    loops: usize,
+    // the body still has its handler to pop, the `catch` does not
+    in_body: bool,
+    // `jump` lines to patch with the `finally` label
+    jumps: Vec<usize>,
+    // where each early exit goes once `finally` has run; `_exit{id}` holds its position plus one
+    exits: Vec<Exit>,
+}
+
+enum Exit {
+    Break,
+    Continue,
+    Return(String),
 }
 
 impl<'a> Compiler<'a> {
+    pub fn new(source: &'a Vec<Option<Statement>>, lines: &'a Vec<usize>) -> Compiler<'a> {
+        Compiler {
+            ir_code: Vec::<String>::new(),
+            source,
+            lines,
+            fxn_depth: 0,
+            loops: Vec::<(Vec<usize>, Vec<usize>)>::new(),
+            ints: HashSet::new(),
+            consts: HashMap::new(),
+            tries: Vec::new(),
+        }
+    }
     pub fn compile(&mut self) -> Result<usize, String> {
-        for s in self.source {
-            match s {
-                Some(_s) => {
-                    self.compile_statement(_s.clone())?;
-                }
-                _ => {}
-            }
+        for _s in self.source.iter().flatten() {
+            self.compile_statement(_s.clone())?;
         }
 
-        return Ok(0);
+        Ok(0)
     }
 
     pub fn compile_statement(&mut self, src: Statement) -> Result<usize, String> {
         match src {
             Statement::Expr(expr) => {
-                let mut encoder = Encoder::new();
-                encoder.encode(expr, self.lines)?;
-                for c in encoder.code {
-                    self.ir_code.push(c);
-                }
+                self.emit_expression(expr)?;
                 // self.counter = self.counter + 1;
-                return Ok(1);
+                Ok(1)
             }
             Statement::Return(expr) => {
-                let mut encoder = Encoder::new();
-                let comp = encoder.encode(expr, self.lines);
-
-                match comp {
-                    Ok(last) => {
-                        for c in encoder.code {
-                            self.ir_code.push(c);
-                        }
-                        self.ir_code.push(format!("print {}", last));
-                    }
-                    Err(exc) => {
-                        return Err(exc);
-                    }
-                }
+                let last = self.emit_expression(expr)?;
+                self.leave(Exit::Return(last));
                 // self.counter = self.counter + 1;
-                return Ok(1);
+                Ok(1)
             }
-            Statement::VarDecl(name, val, _kind,_index) => {
-                let mut encoder = Encoder::new();
-                let tval = encoder.encode(val, self.lines)?;
+            Statement::VarDecl(name, val, kind,_index) => {
+                let tval = self.emit_expression(val)?;
+                self.declare(&name, &kind);
                 self.ir_code.push(format!("new {}", name));
                 self.ir_code.push(format!("{} = {}", name, tval));
                 // self.counter = self.counter + 1;
-                return Ok(1);
+                Ok(1)
+            }
+            Statement::ConstDecl(name, val, kind, _index) => {
+                let folded = literal_value(&val);
+                let tval = self.emit_expression(val)?;
+                // the slot is still made, so exports and references see it
+                self.ir_code.push(format!("new {}", name));
+                self.ir_code.push(format!("{} = {}", name, tval));
+                match folded {
+                    Some(lit) => {
+                        let vtype = match lit {
+                            Value::Int(_) => VarType::Int,
+                            _ => kind,
+                        };
+                        self.declare(&name, &vtype);
+                        self.consts.insert(name, literal(&lit));
+                    }
+                    None => self.declare(&name, &kind),
+                }
+                Ok(1)
             }
             Statement::Block(stmts) => {
                 let block_id = self.ir_code.len();
                 self.ir_code.push(format!("label {}", block_id));
                 let stmt_len = stmts.len();
+                // names declared in the block stop shadowing outer ones after it
+                let outer = (self.ints.clone(), self.consts.clone());
                 for s in stmts {
                     self.compile_statement(s)?;
                 }
+                (self.ints, self.consts) = outer;
                 // self.counter = self.counter + 1;
-                return Ok(stmt_len + 1);
+                Ok(stmt_len + 1)
             }
             Statement::IfStmt(cond, then, otherwise) => {
-                let mut encoder = Encoder::new();
-                let tval = encoder.encode(cond, self.lines)?;
-                for c in encoder.code {
-                    self.ir_code.push(c);
-                }
+                let tval = self.emit_expression(cond)?;
                 let start = self.ir_code.len();
                 self.ir_code.push(format!("jne {} ", tval));
                 let _length = self.compile_statement(*then)?;
-                let end_then = self.ir_code.len();
-                self.ir_code.push(format!("label {}", end_then));
-                self.ir_code[start].push_str(&format!("{}", end_then));
 
                 match *otherwise {
                     Some(other) => {
+                        let skip_else = self.ir_code.len();
                         self.ir_code.push("jump ".to_owned());
-                        let start = self.ir_code.len();
+                        let end_then = self.ir_code.len();
+                        self.ir_code.push(format!("label {}", end_then));
+                        self.ir_code[start].push_str(&format!("{}", end_then));
+
                         let _length = self.compile_statement(other)?;
                         let end_else = self.ir_code.len();
                         self.ir_code.push(format!("label {}", end_else));
-                        self.ir_code[start].push_str(&format!("{}", end_else));
+                        self.ir_code[skip_else].push_str(&format!("{}", end_else));
+                    }
+                    _ => {
+                        let end_then = self.ir_code.len();
+                        self.ir_code.push(format!("label {}", end_then));
+                        self.ir_code[start].push_str(&format!("{}", end_then));
                     }
-                    _ => {}
                 }
 
                 // self.counter = self.counter + 1;
-                return Ok(1);
+                Ok(1)
             }
             Statement::WhileStmt(cond, repeat) => {
-                let mut encoder = Encoder::new();
-                let tval = encoder.encode(cond, self.lines)?;
-                for c in encoder.code {
-                    self.ir_code.push(c);
-                }
                 let start = self.ir_code.len();
                 self.ir_code.push(format!("label {}", start));
+                let tval = self.emit_expression(cond)?;
+                let exit_jump = self.ir_code.len();
                 self.ir_code.push(format!("jne {} ", tval));
+
+                self.loops.push((Vec::<usize>::new(), Vec::<usize>::new()));
                 let _length = self.compile_statement(*repeat)?;
-                self.ir_code.push(format!("je {} {}", tval, start + 2));
-                let end_then = self.ir_code.len();
-                self.ir_code.push(format!("label {}", end_then));
-                self.ir_code[start+1].push_str(&format!("{}", end_then));
+                self.ir_code.push(format!("jump {}", start));
+                let end_loop = self.ir_code.len();
+                self.ir_code.push(format!("label {}", end_loop));
+                self.ir_code[exit_jump].push_str(&format!("{}", end_loop));
+                self.close_loop(start, end_loop);
+
+                Ok(1)
+            }
+            Statement::ForStmt(init, cond, step, repeat) => {
+                if let Some(init_stmt) = *init {
+                    self.compile_statement(init_stmt)?;
+                }
+                let start = self.ir_code.len();
+                self.ir_code.push(format!("label {}", start));
+                let mut exit_jump: Option<usize> = None;
+                if let Some(cond_expr) = cond {
+                    let tval = self.emit_expression(cond_expr)?;
+                    exit_jump = Some(self.ir_code.len());
+                    self.ir_code.push(format!("jne {} ", tval));
+                }
+
+                self.loops.push((Vec::<usize>::new(), Vec::<usize>::new()));
+                let _length = self.compile_statement(*repeat)?;
+                let next = self.ir_code.len();
+                self.ir_code.push(format!("label {}", next));
+                if let Some(step_expr) = step {
+                    self.emit_expression(step_expr)?;
+                }
+                self.ir_code.push(format!("jump {}", start));
+                let end_loop = self.ir_code.len();
+                self.ir_code.push(format!("label {}", end_loop));
+                if let Some(at) = exit_jump {
+                    self.ir_code[at].push_str(&format!("{}", end_loop));
+                }
+                self.close_loop(next, end_loop);
+
+                Ok(1)
+            }
+            Statement::ForEach(name, collection, repeat, _index) => {
+                let tcoll = self.emit_expression(collection)?;
+                let id = self.ir_code.len();
+                let items = format!("_each{}", id);
+                let position = format!("_at{}", id);
+                // `items` gives what the loop goes through: an array's items, a map's keys in the
+                // order they were added, or a string's characters
+                self.ir_code.push(format!("new {}", items));
+                self.ir_code.push(format!("{} = items {}", items, tcoll));
+                self.ir_code.push(format!("new {}", position));
+                self.ir_code.push(format!("{} = 0", position));
+
+                let start = self.ir_code.len();
+                self.ir_code.push(format!("label {}", start));
+                self.ir_code.push(format!("t0 = len {}", items));
+                self.ir_code.push(format!("t1 = {} lt t0", position));
+                let exit_jump = self.ir_code.len();
+                self.ir_code.push("jne t1 ".to_owned());
+                self.ir_code.push(format!("new {}", name));
+                self.ir_code
+                    .push(format!("{} = {}[{}]", name, items, position));
+
+                self.loops.push((Vec::<usize>::new(), Vec::<usize>::new()));
+                // the loop variable shadows outer names only inside the loop
+                let outer = (self.ints.clone(), self.consts.clone());
+                self.declare(&name, &VarType::Var);
+                let _length = self.compile_statement(*repeat)?;
+                (self.ints, self.consts) = outer;
+                let next = self.ir_code.len();
+                self.ir_code.push(format!("label {}", next));
+                self.ir_code
+                    .push(format!("{} = {} + 1", position, position));
+                self.ir_code.push(format!("jump {}", start));
+                let end_loop = self.ir_code.len();
+                self.ir_code.push(format!("label {}", end_loop));
+                self.ir_code[exit_jump].push_str(&format!("{}", end_loop));
+                self.close_loop(next, end_loop);
+
+                Ok(1)
+            }
+            Statement::Match(subject, arms, _index) => {
+                let tval = self.emit_expression(subject)?;
+                let id = self.ir_code.len();
+                let value = format!("_match{}", id);
+                self.ir_code.push(format!("new {}", value));
+                self.ir_code.push(format!("{} = {}", value, tval));
+
+                let mut exits: Vec<usize> = Vec::<usize>::new();
+                for arm in arms {
+                    let mut skip: Option<usize> = None;
+                    if !arm.patterns.iter().any(|p| matches!(p, Pattern::Wildcard)) {
+                        let cond = self.emit_patterns(&value, &arm.patterns);
+                        skip = Some(self.ir_code.len());
+                        self.ir_code.push(format!("jne {} ", cond));
+                    }
+                    let outer = (self.ints.clone(), self.consts.clone());
+                    if let [Pattern::Variant(_, _, names, _)] = arm.patterns.as_slice() {
+                        for (i, name) in names.iter().enumerate().filter(|(_, n)| *n != "_") {
+                            self.ir_code.push(format!("new {}", name));
+                            self.ir_code.push(format!("{} = {}[{}]", name, value, i));
+                            self.declare(name, &VarType::Var);
+                        }
+                    }
+
+                    self.compile_statement(arm.body)?;
+                    (self.ints, self.consts) = outer;
+                    exits.push(self.ir_code.len());
+                    self.ir_code.push("jump ".to_owned());
+                    let next = self.ir_code.len();
+                    self.ir_code.push(format!("label {}", next));
+                    if let Some(at) = skip {
+                        self.ir_code[at].push_str(&format!("{}", next));
+                    }
+                }
+                let end = self.ir_code.len();
+                self.ir_code.push(format!("label {}", end));
+                for at in exits {
+                    self.ir_code[at].push_str(&format!("{}", end));
+                }
+
+                Ok(1)
+            }
+            Statement::Break(index) => {
+                if self.loops.is_empty() {
+                    return error("'break' used outside of a loop", &index, self.lines);
+                }
+                self.leave(Exit::Break);
+                Ok(1)
+            }
+            Statement::Continue(index) => {
+                if self.loops.is_empty() {
+                    return error("'continue' used outside of a loop", &index, self.lines);
+                }
+                self.leave(Exit::Continue);
+                Ok(1)
+            }
+            Statement::Throw(expr, _index) => {
+                let tval = self.emit_expression(expr)?;
+                self.ir_code.push(format!("throw {}", tval));
+                Ok(1)
+            }
+            Statement::Try(body, handler, finally, _index) => {
+                // `try` names the label errors jump to, and `endtry` stops catching them
+                let id = self.ir_code.len();
+                let exit = format!("_exit{}", id);
+                self.ir_code.push(format!("new {}", exit));
+                self.ir_code.push(format!("{} = 0", exit));
+                let start = self.ir_code.len();
+                self.ir_code.push("try ".to_owned());
+                self.tries.push(OpenTry {
+                    id,
+                    loops: self.loops.len(),
+                    in_body: true,
+                    jumps: Vec::new(),
+                    exits: Vec::new(),
+                });
+                self.compile_statement(*body)?;
+                self.ir_code.push("endtry".to_owned());
+                let skip_catch = self.ir_code.len();
+                self.ir_code.push("jump ".to_owned());
+                let catch_at = self.ir_code.len();
+                self.ir_code.push(format!("label {}", catch_at));
+                self.ir_code[start].push_str(&format!("{}", catch_at));
+
+                // without a `catch` the error stays pending and `endfinally` raises it again
+                if let Some((name, catch_body)) = handler {
+                    if let Some(open) = self.tries.last_mut() {
+                        open.in_body = false;
+                    }
+                    self.ir_code.push(format!("new {}", name));
+                    self.ir_code.push(format!("catch {}", name));
+                    let outer = (self.ints.clone(), self.consts.clone());
+                    self.declare(&name, &VarType::Var);
+                    self.compile_statement(*catch_body)?;
+                    (self.ints, self.consts) = outer;
+                }
+                let finally_at = self.ir_code.len();
+                self.ir_code.push(format!("label {}", finally_at));
+                self.ir_code[skip_catch].push_str(&format!("{}", finally_at));
+                let open = self.tries.pop().expect("the try was opened above");
+                for at in open.jumps {
+                    self.ir_code[at].push_str(&format!("{}", finally_at));
+                }
+                if let Some(stmt) = *finally {
+                    self.compile_statement(stmt)?;
+                }
+                self.ir_code.push("endfinally".to_owned());
 
-                return Ok(1);
+                // an early exit carries on from here, through any `try` around this one
+                for (i, next) in open.exits.into_iter().enumerate() {
+                    self.ir_code.push(format!("t0 = {} eq {}", exit, i + 1));
+                    let skip = self.ir_code.len();
+                    self.ir_code.push("jne t0 ".to_owned());
+                    self.leave(next);
+                    let after = self.ir_code.len();
+                    self.ir_code.push(format!("label {}", after));
+                    self.ir_code[skip].push_str(&format!("{}", after));
+                }
+                Ok(1)
+            }
+            Statement::Import(path, _index) => {
+                self.ir_code.push(format!("import {:?}", path));
+                Ok(1)
+            }
+            Statement::Export(inner) => {
+                let name = inner.declared_name();
+                let length = self.compile_statement(*inner)?;
+                if let Some(n) = name {
+                    self.ir_code.push(format!("export {}", n));
+                }
+                Ok(length + 1)
+            }
+            Statement::Doc(_, inner) => self.compile_statement(*inner),
+            Statement::StructDecl(decl) => {
+                self.ir_code.push(format!("struct {}", decl.name));
+                for field in &decl.fields {
+                    self.ir_code.push(format!("field {}", field.name));
+                }
+                self.ir_code.push(format!("endstruct {}", decl.name));
+                Ok(decl.fields.len() + 2)
+            }
+            Statement::EnumDecl(decl) => {
+                self.ir_code.push(format!("enum {}", decl.name));
+                for variant in &decl.variants {
+                    self.ir_code
+                        .push(format!("variant {} {}", variant.name, variant.payload.len()));
+                }
+                self.ir_code.push(format!("endenum {}", decl.name));
+                Ok(decl.variants.len() + 2)
+            }
+            Statement::FxnDecl(decl) => {
+                self.compile_fxn(&decl.name, &decl)?;
+                Ok(decl.body.len() + 2)
+            }
+        }
+    }
+
+    fn compile_fxn(&mut self, name: &str, decl: &FunctionDecl) -> Result<(), String> {
+        self.ir_code.push(format!("func {}", name));
+        let outer = (self.ints.clone(), self.consts.clone());
+        for param in &decl.params {
+            self.ir_code.push(format!("param {}", param.name));
+            self.declare(&param.name, &param.ptype);
+        }
+        // loops around the function body do not reach into it
+        let enclosing_loops = std::mem::take(&mut self.loops);
+        let enclosing_tries = std::mem::take(&mut self.tries);
+        self.fxn_depth += 1;
+        for s in decl.body.clone() {
+            self.compile_statement(s)?;
+        }
+        self.fxn_depth -= 1;
+        self.loops = enclosing_loops;
+        self.tries = enclosing_tries;
+        (self.ints, self.consts) = outer;
+        self.ir_code.push("ret".to_owned());
+        self.ir_code.push(format!("endfunc {}", name));
+        Ok(())
+    }
+
+    /// Tests `value` against each pattern and returns the temporary that holds whether any matched.
+    fn emit_patterns(&mut self, value: &str, patterns: &[Pattern]) -> String {
+        let mut counter: usize = 0;
+        let mut cond: Option<String> = None;
+        for p in patterns {
+            let test = format!("t{}", counter);
+            match p {
+                Pattern::Range(from, to) => {
+                    self.ir_code
+                        .push(format!("t{} = {} gte {}", counter + 1, value, from));
+                    self.ir_code
+                        .push(format!("t{} = {} lt {}", counter + 2, value, to));
+                    self.ir_code.push(format!(
+                        "{} = t{} and t{}",
+                        test,
+                        counter + 1,
+                        counter + 2
+                    ));
+                    counter += 3;
+                }
+                Pattern::Literal(lit) => {
+                    self.ir_code
+                        .push(format!("{} = {} eq {}", test, value, literal(lit)));
+                    counter += 1;
+                }
+                Pattern::Variant(_, variant, _, _) => {
+                    self.ir_code
+                        .push(format!("t{} = tag {}", counter + 1, value));
+                    self.ir_code
+                        .push(format!("{} = t{} eq '{}'", test, counter + 1, variant));
+                    counter += 2;
+                }
+                Pattern::Wildcard => continue,
+            }
+            cond = match cond {
+                Some(prev) => {
+                    self.ir_code
+                        .push(format!("t{} = {} or {}", counter, prev, test));
+                    counter += 1;
+                    Some(format!("t{}", counter - 1))
+                }
+                None => Some(test),
+            };
+        }
+        cond.unwrap_or_else(|| "true".to_owned())
+    }
+
+    /// Appends the code for `expr`, followed by the bodies of any anonymous functions in it.
+    fn emit_expression(&mut self, expr: Expression) -> Result<String, String> {
+        let mut encoder =
+            Encoder::new(self.ir_code.len(), self.ints.clone(), self.consts.clone());
+        let tval = encoder.encode(expr, self.lines)?;
+        for c in encoder.code {
+            self.ir_code.push(c);
+        }
+        for (name, decl) in encoder.lambdas {
+            self.compile_fxn(&name, &decl)?;
+        }
+        Ok(tval)
+    }
+
+    fn declare(&mut self, name: &str, vtype: &VarType) {
+        self.consts.remove(name);
+        if *vtype == VarType::Int {
+            self.ints.insert(name.to_owned());
+        } else {
+            self.ints.remove(name);
+        }
+    }
+
+    /// Points the pending `continue` and `break` jumps of the innermost loop at their labels.
+    /// Jumps out for `break`, `continue` or `return`. Leaving a `try` first pops its handler and
+    /// goes to its `finally`, which sends the exit on once it is done.
+    fn leave(&mut self, exit: Exit) {
+        let crossed = match (&exit, self.tries.last()) {
+            (_, None) => false,
+            (Exit::Return(_), Some(_)) => true,
+            (_, Some(open)) => open.loops >= self.loops.len(),
+        };
+        if let (true, Some(open)) = (crossed, self.tries.last_mut()) {
+            if open.in_body {
+                self.ir_code.push("endtry".to_owned());
+            }
+            // the value is kept aside, as `finally` may use the temporaries
+            let exit = match exit {
+                Exit::Return(val) => {
+                    let kept = format!("_ret{}", open.id);
+                    self.ir_code.push(format!("new {}", kept));
+                    self.ir_code.push(format!("{} = {}", kept, val));
+                    Exit::Return(kept)
+                }
+                other => other,
+            };
+            open.exits.push(exit);
+            self.ir_code
+                .push(format!("_exit{} = {}", open.id, open.exits.len()));
+            open.jumps.push(self.ir_code.len());
+            self.ir_code.push("jump ".to_owned());
+            return;
+        }
+
+        let at = self.ir_code.len();
+        match exit {
+            Exit::Break => {
+                if let Some((_, breaks)) = self.loops.last_mut() {
+                    breaks.push(at);
+                }
+                self.ir_code.push("jump ".to_owned());
+            }
+            Exit::Continue => {
+                if let Some((continues, _)) = self.loops.last_mut() {
+                    continues.push(at);
+                }
+                self.ir_code.push("jump ".to_owned());
+            }
+            Exit::Return(val) if self.fxn_depth > 0 => self.ir_code.push(format!("ret {}", val)),
+            Exit::Return(val) => self.ir_code.push(format!("halt {}", val)),
+        }
+    }
+
+    fn close_loop(&mut self, continue_at: usize, end_loop: usize) {
+        if let Some((continues, breaks)) = self.loops.pop() {
+            for c in continues {
+                self.ir_code[c].push_str(&format!("{}", continue_at));
+            }
+            for b in breaks {
+                self.ir_code[b].push_str(&format!("{}", end_loop));
             }
         }
     }
@@ -141,3 +570,19 @@ impl<'a> Compiler<'a> {
     //     return cmp.ir_code;
     // }
 }
+
+/// The value of a literal, or of a negated number literal, known before the program runs.
+fn literal_value(expr: &Expression) -> Option<Value> {
+    match expr {
+        Expression::Literal(val, _) => Some(val.clone()),
+        Expression::Group(inner) => literal_value(inner),
+        Expression::Unary(sign, inner) if sign.ttype == TokenType::Minus => {
+            match literal_value(inner)? {
+                Value::Number(n) => Some(Value::Number(-n)),
+                Value::Int(n) => n.checked_neg().map(Value::Int),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
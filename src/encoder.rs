@@ -1,28 +1,45 @@
-use crate::expression::Expression;
+use crate::expression::{Expression, Value};
+use crate::function::FunctionDecl;
 use crate::tokenizer::TokenType;
+use crate::var_type::VarType;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
 pub struct Encoder {
     pub counter: usize,
     pub code: Vec<String>,
+    // IR line the encoded code will start at, so labels line up with the compiler's
+    pub base: usize,
+    // anonymous functions met while encoding, for the compiler to emit after the code
+    pub lambdas: Vec<(String, Rc<FunctionDecl>)>,
+    // variables declared `int`, whose arithmetic uses the integer instructions
+    pub ints: HashSet<String>,
+    // constants folded to the literal they were declared with
+    pub consts: HashMap<String, String>,
 }
 
 impl Encoder {
-    pub fn new() -> Encoder{
+    pub fn new(base: usize, ints: HashSet<String>, consts: HashMap<String, String>) -> Encoder{
         Encoder{
             counter:0,
-            code:Vec::<String>::new()
+            code:Vec::<String>::new(),
+            base,
+            lambdas:Vec::new(),
+            ints,
+            consts
         }
     }
-    pub fn encode(&mut self, expr: Expression, lines: &Vec<usize>) -> Result<String, String> {
+    pub fn encode(&mut self, expr: Expression, lines: &[usize]) -> Result<String, String> {
         match expr {
-            Expression::Literal(val, _index) => {
-                return Ok(format!("{:?}", val));
-            }
+            Expression::Literal(val, _index) => Ok(literal(&val)),
             Expression::Variable(name, _index) => {
+                if let Some(lit) = self.consts.get(&name) {
+                    return Ok(lit.clone());
+                }
                 self.code.push(format!("t{} = {}", self.counter, name));
-                self.counter = self.counter + 1;
+                self.counter += 1;
 
-                return Ok(format!("t{}", self.counter - 1));
+                Ok(format!("t{}", self.counter - 1))
             }
             Expression::Unary(sign, val) => {
                 let texpr = self.encode(*val, lines)?;
@@ -30,147 +47,451 @@ impl Encoder {
                     TokenType::Bang => {
                         self.code
                             .push(format!("t{} = invert {}", self.counter, texpr));
-                        self.counter = self.counter + 1;
-                        return Ok(format!("t{}", self.counter - 1));
+                        self.counter += 1;
+                        Ok(format!("t{}", self.counter - 1))
                     }
                     TokenType::Minus => {
                         self.code
                             .push(format!("t{} = uneg {}", self.counter, texpr));
-                        self.counter = self.counter + 1;
-                        return Ok(format!("t{}", self.counter - 1));
+                        self.counter += 1;
+                        Ok(format!("t{}", self.counter - 1))
                     }
-                    _ => {
-                        return error("Illegal unary operation", &sign.index, lines);
+                    TokenType::Tilde => {
+                        self.code
+                            .push(format!("t{} = bnot {}", self.counter, texpr));
+                        self.counter += 1;
+                        Ok(format!("t{}", self.counter - 1))
                     }
+                    _ => error("Illegal unary operation", &sign.index, lines),
                 }
             }
             Expression::Binary(left, sign, right) => {
-                let left_expr = self.encode(*left, lines)?;
-                let right_expr = self.encode(*right, lines)?;
+                if sign.ttype == TokenType::And || sign.ttype == TokenType::Or {
+                    return self.encode_logical(*left, sign.ttype, *right, lines);
+                }
+                if let Some(op) = bitwise_op(&sign.ttype) {
+                    let left_expr = self.encode(*left, lines)?;
+                    let right_expr = self.encode(*right, lines)?;
+                    self.code.push(format!(
+                        "t{} = {} {} {}",
+                        self.counter, left_expr, op, right_expr
+                    ));
+                    self.counter += 1;
+                    return Ok(format!("t{}", self.counter - 1));
+                }
+                let both_int = self.is_int(&left) && self.is_int(&right);
+                let mut left_expr = self.encode(*left, lines)?;
+                let mut right_expr = self.encode(*right, lines)?;
+                if both_int {
+                    match integer_op(&sign.ttype) {
+                        Some(op) => {
+                            self.code.push(format!(
+                                "t{} = {} {} {}",
+                                self.counter, left_expr, op, right_expr
+                            ));
+                            self.counter += 1;
+                            return Ok(format!("t{}", self.counter - 1));
+                        }
+                        // dividing two ints gives a num
+                        None if sign.ttype == TokenType::Slash => {
+                            left_expr = self.widen(left_expr);
+                            right_expr = self.widen(right_expr);
+                        }
+                        None => {}
+                    }
+                }
 
                 match sign.ttype {
-                    TokenType::And => {
-                        self.code.push(format!(
-                            "t{} = {} and {}",
-                            self.counter, left_expr, right_expr
-                        ));
-                        self.counter = self.counter + 1;
-                        return Ok(format!("t{}", self.counter - 1));
-                    }
-                    TokenType::Or => {
-                        self.code.push(format!(
-                            "t{} = {} or {}",
-                            self.counter, left_expr, right_expr
-                        ));
-                        self.counter = self.counter + 1;
-                        return Ok(format!("t{}", self.counter - 1));
-                    }
                     TokenType::Plus => {
                         self.code.push(format!(
                             "t{} = {} + {}",
                             self.counter, left_expr, right_expr
                         ));
-                        self.counter = self.counter + 1;
-                        return Ok(format!("t{}", self.counter - 1));
+                        self.counter += 1;
+                        Ok(format!("t{}", self.counter - 1))
                     }
                     TokenType::Minus => {
                         self.code.push(format!(
                             "t{} = {} - {}",
                             self.counter, left_expr, right_expr
                         ));
-                        self.counter = self.counter + 1;
-                        return Ok(format!("t{}", self.counter - 1));
+                        self.counter += 1;
+                        Ok(format!("t{}", self.counter - 1))
                     }
                     TokenType::Star => {
                         self.code.push(format!(
                             "t{} = {} * {}",
                             self.counter, left_expr, right_expr
                         ));
-                        self.counter = self.counter + 1;
-                        return Ok(format!("t{}", self.counter - 1));
+                        self.counter += 1;
+                        Ok(format!("t{}", self.counter - 1))
                     }
                     TokenType::Slash => {
                         self.code.push(format!(
                             "t{} = {} / {}",
                             self.counter, left_expr, right_expr
                         ));
-                        self.counter = self.counter + 1;
-                        return Ok(format!("t{}", self.counter - 1));
+                        self.counter += 1;
+                        Ok(format!("t{}", self.counter - 1))
                     }
                     TokenType::Mod => {
                         self.code.push(format!(
                             "t{} = {} % {}",
                             self.counter, left_expr, right_expr
                         ));
-                        self.counter = self.counter + 1;
-                        return Ok(format!("t{}", self.counter - 1));
+                        self.counter += 1;
+                        Ok(format!("t{}", self.counter - 1))
+                    }
+                    TokenType::Power => {
+                        self.code.push(format!(
+                            "t{} = {} pow {}",
+                            self.counter, left_expr, right_expr
+                        ));
+                        self.counter += 1;
+                        Ok(format!("t{}", self.counter - 1))
                     }
                     TokenType::Equality => {
                         self.code.push(format!(
                             "t{} = {} eq {}",
                             self.counter, left_expr, right_expr
                         ));
-                        self.counter = self.counter + 1;
-                        return Ok(format!("t{}", self.counter - 1));
+                        self.counter += 1;
+                        Ok(format!("t{}", self.counter - 1))
                     }
                     TokenType::BangEquals => {
                         self.code.push(format!(
                             "t{} = {} neq {}",
                             self.counter, left_expr, right_expr
                         ));
-                        self.counter = self.counter + 1;
-                        return Ok(format!("t{}", self.counter - 1));
+                        self.counter += 1;
+                        Ok(format!("t{}", self.counter - 1))
                     }
                     TokenType::Greater => {
                         self.code.push(format!(
                             "t{} = {} gt {}",
                             self.counter, left_expr, right_expr
                         ));
-                        self.counter = self.counter + 1;
-                        return Ok(format!("t{}", self.counter - 1));
+                        self.counter += 1;
+                        Ok(format!("t{}", self.counter - 1))
                     }
                     TokenType::GreaterEquals => {
                         self.code.push(format!(
                             "t{} = {} gte {}",
                             self.counter, left_expr, right_expr
                         ));
-                        self.counter = self.counter + 1;
-                        return Ok(format!("t{}", self.counter - 1));
+                        self.counter += 1;
+                        Ok(format!("t{}", self.counter - 1))
                     }
                     TokenType::Lesser => {
                         self.code.push(format!(
                             "t{} = {} lt {}",
                             self.counter, left_expr, right_expr
                         ));
-                        self.counter = self.counter + 1;
-                        return Ok(format!("t{}", self.counter - 1));
+                        self.counter += 1;
+                        Ok(format!("t{}", self.counter - 1))
                     }
                     TokenType::LesserEquals => {
                         self.code.push(format!(
                             "t{} = {} lte {}",
                             self.counter, left_expr, right_expr
                         ));
-                        self.counter = self.counter + 1;
-                        return Ok(format!("t{}", self.counter - 1));
+                        self.counter += 1;
+                        Ok(format!("t{}", self.counter - 1))
                     }
-                    _ => return error("Illegal binary operation", &sign.index, lines),
+                    _ => error("Illegal binary operation", &sign.index, lines),
                 }
             }
             Expression::Assignment(name,val ,_index ) => {
                 let tval = self.encode(*val, lines)?;
                 self.code.push(format!("{} = {}",name,tval));
-                return Ok(tval);
+                Ok(tval)
             }
             Expression::Group(val) => {
                 let tval = self.encode(*val, lines)?;
-                return Ok(tval);
+                Ok(tval)
+            }
+            Expression::Call(callee, args, _index) => {
+                let target = match *callee {
+                    Expression::Variable(name, _) => name,
+                    other => self.encode(other, lines)?,
+                };
+                let argc = args.len();
+                let mut targs: Vec<String> = Vec::<String>::new();
+                for arg in args {
+                    targs.push(self.encode(arg, lines)?);
+                }
+                for targ in targs {
+                    self.code.push(format!("arg {}", targ));
+                }
+                self.code
+                    .push(format!("t{} = call {} {}", self.counter, target, argc));
+                self.counter += 1;
+                Ok(format!("t{}", self.counter - 1))
+            }
+            Expression::Reference(name, _index) => {
+                self.code.push(format!("t{} = ref {}", self.counter, name));
+                self.counter += 1;
+                Ok(format!("t{}", self.counter - 1))
+            }
+            Expression::Query(val, _index) => {
+                let texpr = match *val {
+                    Expression::Variable(name, _) => name,
+                    other => self.encode(other, lines)?,
+                };
+                self.code.push(format!("t{} = valid {}", self.counter, texpr));
+                self.counter += 1;
+                Ok(format!("t{}", self.counter - 1))
+            }
+            Expression::ArrayLiteral(items, _index) => {
+                let arr = format!("t{}", self.counter);
+                self.code.push(format!("{} = array {}", arr, items.len()));
+                self.counter += 1;
+                for (i, item) in items.into_iter().enumerate() {
+                    let titem = self.encode(item, lines)?;
+                    self.code.push(format!("{}[{}] = {}", arr, i, titem));
+                }
+                Ok(arr)
+            }
+            Expression::MapLiteral(entries, _index) => {
+                let map = format!("t{}", self.counter);
+                self.code.push(format!("{} = map {}", map, entries.len()));
+                self.counter += 1;
+                for (key, val) in entries {
+                    let tkey = self.encode(key, lines)?;
+                    let tval = self.encode(val, lines)?;
+                    self.code.push(format!("{}[{}] = {}", map, tkey, tval));
+                }
+                Ok(map)
+            }
+            Expression::Index(arr, idx, _index) => {
+                let tarr = self.encode(*arr, lines)?;
+                let tidx = self.encode(*idx, lines)?;
+                self.code
+                    .push(format!("t{} = {}[{}]", self.counter, tarr, tidx));
+                self.counter += 1;
+                Ok(format!("t{}", self.counter - 1))
+            }
+            Expression::Get(obj, name, _index) => {
+                let tobj = self.encode(*obj, lines)?;
+                self.code
+                    .push(format!("t{} = {}.{}", self.counter, tobj, name));
+                self.counter += 1;
+                Ok(format!("t{}", self.counter - 1))
+            }
+            Expression::SetField(obj, name, val, _index) => {
+                let tobj = self.encode(*obj, lines)?;
+                let tval = self.encode(*val, lines)?;
+                self.code.push(format!("{}.{} = {}", tobj, name, tval));
+                Ok(tval)
+            }
+            Expression::Convert(vtype, val, _index) => {
+                let texpr = self.encode(*val, lines)?;
+                let op = match vtype {
+                    VarType::Int => "ftoi",
+                    _ => "itof",
+                };
+                self.code
+                    .push(format!("t{} = {} {}", self.counter, op, texpr));
+                self.counter += 1;
+                Ok(format!("t{}", self.counter - 1))
+            }
+            Expression::Lambda(decl) => {
+                let name = format!("lambda{}", self.base + self.code.len());
+                self.code
+                    .push(format!("t{} = closure {}", self.counter, name));
+                self.lambdas.push((name, decl));
+                self.counter += 1;
+                Ok(format!("t{}", self.counter - 1))
             }
+            Expression::StructLiteral(name, fields, _index) => {
+                let inst = format!("t{}", self.counter);
+                self.code.push(format!("{} = struct {}", inst, name));
+                self.counter += 1;
+                for (field, val) in fields {
+                    let tval = self.encode(val, lines)?;
+                    self.code.push(format!("{}.{} = {}", inst, field, tval));
+                }
+                Ok(inst)
+            }
+            Expression::IndexAssignment(arr, idx, val, _index) => {
+                let tarr = self.encode(*arr, lines)?;
+                let tidx = self.encode(*idx, lines)?;
+                let tval = self.encode(*val, lines)?;
+                self.code.push(format!("{}[{}] = {}", tarr, tidx, tval));
+                Ok(tval)
+            }
+            Expression::CompoundAssignment(target, sign, val) => match *target {
+                Expression::Variable(name, _index) => {
+                    let both_int = self.ints.contains(&name) && self.is_int(&val);
+                    let tval = self.encode(*val, lines)?;
+                    let op = match integer_op(&sign.ttype) {
+                        Some(op) if both_int => op,
+                        _ => arithmetic_op(&sign.ttype),
+                    };
+                    self.code.push(format!(
+                        "t{} = {} {} {}",
+                        self.counter, name, op, tval
+                    ));
+                    self.code.push(format!("{} = t{}", name, self.counter));
+                    self.counter += 1;
+                    Ok(format!("t{}", self.counter - 1))
+                }
+                Expression::Index(arr, idx, _index) => {
+                    let tarr = self.encode(*arr, lines)?;
+                    let tidx = self.encode(*idx, lines)?;
+                    let tval = self.encode(*val, lines)?;
+                    self.code
+                        .push(format!("t{} = {}[{}]", self.counter, tarr, tidx));
+                    self.code.push(format!(
+                        "t{} = t{} {} {}",
+                        self.counter + 1,
+                        self.counter,
+                        arithmetic_op(&sign.ttype),
+                        tval
+                    ));
+                    self.code
+                        .push(format!("{}[{}] = t{}", tarr, tidx, self.counter + 1));
+                    self.counter += 2;
+                    Ok(format!("t{}", self.counter - 1))
+                }
+                Expression::Get(obj, name, _index) => {
+                    let tobj = self.encode(*obj, lines)?;
+                    let tval = self.encode(*val, lines)?;
+                    self.code
+                        .push(format!("t{} = {}.{}", self.counter, tobj, name));
+                    self.code.push(format!(
+                        "t{} = t{} {} {}",
+                        self.counter + 1,
+                        self.counter,
+                        arithmetic_op(&sign.ttype),
+                        tval
+                    ));
+                    self.code
+                        .push(format!("{}.{} = t{}", tobj, name, self.counter + 1));
+                    self.counter += 2;
+                    Ok(format!("t{}", self.counter - 1))
+                }
+                _ => error("Invalid assignment target", &sign.index, lines),
+            },
             _ => error("Not implemented", &0, lines)
         }
     }
+
+    /// Whether `expr` is known to give an `int` without running it.
+    fn is_int(&self, expr: &Expression) -> bool {
+        match expr {
+            Expression::Literal(Value::Int(_), _) => true,
+            Expression::Variable(name, _) => self.ints.contains(name),
+            Expression::Group(val) => self.is_int(val),
+            Expression::Unary(sign, val) => match sign.ttype {
+                TokenType::Tilde => true,
+                TokenType::Minus => self.is_int(val),
+                _ => false,
+            },
+            Expression::Binary(left, sign, right) => {
+                bitwise_op(&sign.ttype).is_some()
+                    || (integer_op(&sign.ttype).is_some() && self.is_int(left) && self.is_int(right))
+            }
+            Expression::Convert(vtype, _, _) => *vtype == VarType::Int,
+            _ => false,
+        }
+    }
+
+    fn widen(&mut self, texpr: String) -> String {
+        self.code
+            .push(format!("t{} = itof {}", self.counter, texpr));
+        self.counter += 1;
+        format!("t{}", self.counter - 1)
+    }
+
+    /// `&` and `|` jump over the right side once the left side decides the result.
+    fn encode_logical(
+        &mut self,
+        left: Expression,
+        sign: TokenType,
+        right: Expression,
+        lines: &[usize],
+    ) -> Result<String, String> {
+        let left_expr = self.encode(left, lines)?;
+        let result = format!("t{}", self.counter);
+        self.counter += 1;
+        self.code.push(format!("{} = {}", result, left_expr));
+
+        if sign == TokenType::Or {
+            self.code
+                .push(format!("t{} = invert {}", self.counter, result));
+            self.code.push(format!("jne t{} ", self.counter));
+            self.counter += 1;
+        } else {
+            self.code.push(format!("jne {} ", result));
+        }
+        let jump = self.code.len() - 1;
+
+        let right_expr = self.encode(right, lines)?;
+        self.code.push(format!("{} = {}", result, right_expr));
+        let end = self.base + self.code.len();
+        self.code.push(format!("label {}", end));
+        self.code[jump].push_str(&format!("{}", end));
+
+        Ok(result)
+    }
+}
+
+/// A literal as IR code. Strings are escaped so that each instruction stays on one line.
+pub fn literal(val: &Value) -> String {
+    let Value::StringVal(text) = val else {
+        return format!("{:?}", val);
+    };
+    let mut quoted = String::from("'");
+    for c in text.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '\'' => quoted.push_str("\\'"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\0' => quoted.push_str("\\0"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+fn arithmetic_op(ttype: &TokenType) -> &'static str {
+    match ttype {
+        TokenType::Plus => "+",
+        TokenType::Minus => "-",
+        TokenType::Star => "*",
+        TokenType::Slash => "/",
+        _ => "%",
+    }
+}
+
+/// Checked integer instructions, which stop with an overflow error instead of rounding.
+fn integer_op(ttype: &TokenType) -> Option<&'static str> {
+    match ttype {
+        TokenType::Plus => Some("iadd"),
+        TokenType::Minus => Some("isub"),
+        TokenType::Star => Some("imul"),
+        TokenType::Mod => Some("imod"),
+        TokenType::Power => Some("ipow"),
+        _ => None,
+    }
+}
+
+/// Bitwise instructions, which take whole numbers and always give an `int`.
+fn bitwise_op(ttype: &TokenType) -> Option<&'static str> {
+    match ttype {
+        TokenType::BitAnd => Some("band"),
+        TokenType::BitOr => Some("bor"),
+        TokenType::BitXor => Some("bxor"),
+        TokenType::ShiftLeft => Some("shl"),
+        TokenType::ShiftRight => Some("shr"),
+        _ => None,
+    }
 }
 
-fn get_line_from_index(lines: &Vec<usize>, index: &usize) -> usize {
+fn get_line_from_index(lines: &[usize], index: &usize) -> usize {
     // Find the first newline index greater than the given index
     match lines.binary_search(index) {
         // If the index matches exactly a newline index, return the corresponding line number
@@ -180,8 +501,8 @@ fn get_line_from_index(lines: &Vec<usize>, index: &usize) -> usize {
     }
 }
 
-fn error(msg: &str, index: &usize, lines: &Vec<usize>) -> Result<String, String> {
+fn error(msg: &str, index: &usize, lines: &[usize]) -> Result<String, String> {
     let l = get_line_from_index(lines, index);
     // let c = get_col(index, lines);
-    return Result::Err(format!("{} at line {}", msg, l));
+    Result::Err(format!("{} at line {}", msg, l))
 }
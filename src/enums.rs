@@ -0,0 +1,120 @@
+use crate::{
+    badger_debug::{error, RuntimeError},
+    expression::Value,
+    var_type::VarType,
+};
+use std::rc::Rc;
+
+pub struct Variant {
+    pub name: String,
+    // the types of the values the variant carries, empty for a plain tag
+    pub payload: Vec<VarType>,
+    pub index: usize,
+}
+
+pub struct EnumDecl {
+    pub name: String,
+    pub variants: Vec<Variant>,
+    pub index: usize,
+}
+
+pub struct EnumValue {
+    pub decl: Rc<EnumDecl>,
+    // position of the variant in the declaration
+    pub tag: usize,
+    pub payload: Vec<Value>,
+}
+
+impl EnumDecl {
+    /// Builds `Enum.Variant(args)`, checking the values against the declared payload.
+    pub fn construct(
+        decl: &Rc<EnumDecl>,
+        name: &str,
+        args: Vec<Value>,
+        index: &usize,
+        lines: &[usize],
+    ) -> Result<Value, RuntimeError> {
+        let tag = decl.variant_position(name, index, lines)?;
+        let variant = &decl.variants[tag];
+        if args.len() != variant.payload.len() {
+            return error(
+                &format!(
+                    "Variant '{}.{}' takes {} values but got {}",
+                    decl.name,
+                    variant.name,
+                    variant.payload.len(),
+                    args.len()
+                ),
+                index,
+                lines,
+            );
+        }
+
+        let mut payload: Vec<Value> = Vec::<Value>::new();
+        for (i, (val, vtype)) in args.into_iter().zip(variant.payload.iter()).enumerate() {
+            if !val.is_of_type(vtype) {
+                return error(
+                    &format!(
+                        "Value {} of '{}.{}' is not of expected type ({})",
+                        i + 1,
+                        decl.name,
+                        variant.name,
+                        vtype
+                    ),
+                    index,
+                    lines,
+                );
+            }
+            payload.push(val.coerce(vtype));
+        }
+
+        Ok(Value::Variant(Rc::new(EnumValue {
+            decl: Rc::clone(decl),
+            tag,
+            payload,
+        })))
+    }
+
+    /// `Enum.Variant` without a call, which is only a value for variants that carry nothing.
+    pub fn unit(
+        decl: &Rc<EnumDecl>,
+        name: &str,
+        index: &usize,
+        lines: &[usize],
+    ) -> Result<Value, RuntimeError> {
+        let tag = decl.variant_position(name, index, lines)?;
+        if !decl.variants[tag].payload.is_empty() {
+            return error(
+                &format!(
+                    "Variant '{}.{}' needs its values, as in '{}.{}(...)'",
+                    decl.name, name, decl.name, name
+                ),
+                index,
+                lines,
+            );
+        }
+        EnumDecl::construct(decl, name, Vec::new(), index, lines)
+    }
+
+    fn variant_position(
+        &self,
+        name: &str,
+        index: &usize,
+        lines: &[usize],
+    ) -> Result<usize, RuntimeError> {
+        match self.variants.iter().position(|v| v.name == name) {
+            Some(at) => Ok(at),
+            None => error(
+                &format!("'{}' has no variant named '{}'", self.name, name),
+                index,
+                lines,
+            ),
+        }
+    }
+}
+
+impl EnumValue {
+    pub fn name(&self) -> &str {
+        &self.decl.variants[self.tag].name
+    }
+}
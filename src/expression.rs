@@ -1,11 +1,17 @@
 use crate::{
-    badger_debug::{error, get_col, get_line_from_index},
+    badger_debug::{error, RuntimeError},
+    builtins::NativeFunction,
+    enums::{EnumDecl, EnumValue},
+    function::{Function, FunctionDecl},
+    module::Module,
+    structs::{Instance, StructDecl},
     symbol_table::SymbolTable,
     tokenizer::{Token, TokenType},
+    var_type::VarType,
 };
 use std::cell::RefCell;
 use std::fmt;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 
 #[derive(Clone)]
 pub enum Expression {
@@ -16,6 +22,21 @@ pub enum Expression {
     Binary(Box<Expression>, Token, Box<Expression>),
     Group(Box<Expression>),
     Assignment(String, Box<Expression>, usize),
+    Call(Box<Expression>, Vec<Expression>, usize),
+    Reference(String, usize),
+    Query(Box<Expression>, usize),
+    ArrayLiteral(Vec<Expression>, usize),
+    MapLiteral(Vec<(Expression, Expression)>, usize),
+    Index(Box<Expression>, Box<Expression>, usize),
+    IndexAssignment(Box<Expression>, Box<Expression>, Box<Expression>, usize),
+    Get(Box<Expression>, String, usize),
+    SetField(Box<Expression>, String, Box<Expression>, usize),
+    StructLiteral(String, Vec<(String, Expression)>, usize),
+    Lambda(Rc<FunctionDecl>),
+    // `target op= value`, with the arithmetic operator it applies
+    CompoundAssignment(Box<Expression>, Token, Box<Expression>),
+    // `int(x)` or `num(x)`
+    Convert(VarType, Box<Expression>, usize),
 }
 
 impl fmt::Debug for Expression {
@@ -28,6 +49,23 @@ impl fmt::Debug for Expression {
             Expression::Binary(l, s, r) => write!(f, "({:?} {:?} {:?}) ", l, s, r),
             Expression::Variable(name, _iindex) => write!(f, "{:?}", name),
             Expression::Assignment(lhs, rhs, _) => write!(f, "{:?} = {:?}", lhs,rhs),
+            Expression::Call(callee, args, _) => write!(f, "{:?}({:?}) ", callee, args),
+            Expression::Reference(name, _) => write!(f, "@{:?} ", name),
+            Expression::Query(ex, _) => write!(f, "?{:?} ", ex),
+            Expression::ArrayLiteral(items, _) => write!(f, "{:?} ", items),
+            Expression::MapLiteral(entries, _) => write!(f, "{:?} ", entries),
+            Expression::Index(arr, idx, _) => write!(f, "{:?}[{:?}] ", arr, idx),
+            Expression::IndexAssignment(arr, idx, val, _) => {
+                write!(f, "{:?}[{:?}] = {:?}", arr, idx, val)
+            }
+            Expression::Get(obj, name, _) => write!(f, "{:?}.{} ", obj, name),
+            Expression::SetField(obj, name, val, _) => write!(f, "{:?}.{} = {:?}", obj, name, val),
+            Expression::StructLiteral(name, fields, _) => write!(f, "{} {:?} ", name, fields),
+            Expression::Lambda(decl) => write!(f, "<fxn {}> ", decl.name),
+            Expression::Convert(vtype, val, _) => write!(f, "{}({:?}) ", vtype, val),
+            Expression::CompoundAssignment(target, op, val) => {
+                write!(f, "{:?} {:?}= {:?}", target, op, val)
+            }
         }
     }
 }
@@ -35,8 +73,21 @@ impl fmt::Debug for Expression {
 #[derive(Clone)]
 pub enum Value {
     Number(f64),
+    Int(i64),
     StringVal(String),
     Boolean(bool),
+    Function(Rc<Function>),
+    Native(Rc<NativeFunction>),
+    Reference(Weak<RefCell<SymbolTable>>, String),
+    Array(Rc<RefCell<Vec<Value>>>),
+    // string keyed entries, kept in insertion order
+    Map(Rc<RefCell<Vec<(String, Value)>>>),
+    Null,
+    Module(Rc<Module>),
+    Struct(Rc<StructDecl>),
+    Instance(Rc<Instance>),
+    Enum(Rc<EnumDecl>),
+    Variant(Rc<EnumValue>),
 }
 
 impl fmt::Debug for Value {
@@ -44,7 +95,234 @@ impl fmt::Debug for Value {
         match self {
             Self::Boolean(b) => write!(f, "{}", b),
             Self::Number(n) => write!(f, "{}", n),
+            Self::Int(n) => write!(f, "{}", n),
             Self::StringVal(s) => write!(f, "'{}'", s),
+            Self::Function(fx) => write!(f, "<fxn {}>", fx.decl.name),
+            Self::Native(fx) => write!(f, "<fxn {}>", fx.name),
+            Self::Reference(_, name) => write!(f, "<ref {}>", name),
+            Self::Array(items) => write!(f, "{:?}", items.borrow()),
+            Self::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, val)) in entries.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "'{}': {:?}", key, val)?;
+                }
+                write!(f, "}}")
+            }
+            Self::Null => write!(f, "null"),
+            Self::Module(m) => write!(f, "<module {}>", m.name),
+            Self::Struct(s) => write!(f, "<struct {}>", s.name),
+            Self::Instance(inst) => {
+                write!(f, "{} {{ ", inst.decl.name)?;
+                for (i, (field, val)) in inst.decl.fields.iter().zip(inst.values.borrow().iter()).enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {:?}", field.name, val)?;
+                }
+                write!(f, " }}")
+            }
+            Self::Enum(e) => write!(f, "<enum {}>", e.name),
+            Self::Variant(v) => {
+                write!(f, "{}.{}", v.decl.name, v.name())?;
+                if !v.payload.is_empty() {
+                    let parts: Vec<String> = v.payload.iter().map(|p| format!("{:?}", p)).collect();
+                    write!(f, "({})", parts.join(", "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::StringVal(s) => write!(f, "{}", s),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl Value {
+    pub fn is_of_type(&self, vtype: &VarType) -> bool {
+        if *vtype == VarType::Var {
+            return true;
+        }
+        if let VarType::Nullable(inner) = vtype {
+            return matches!(self, Value::Null) || self.is_of_type(inner);
+        }
+        if let Value::Reference(slot, name) = self {
+            return match slot.upgrade() {
+                // the slot's table is busy only while it is itself being assigned to
+                Some(tbl) => match tbl.try_borrow() {
+                    Ok(t) => match t.map.get(name) {
+                        Some(val) => val.is_of_type(vtype),
+                        None => false,
+                    },
+                    Err(_) => true,
+                },
+                None => false,
+            };
+        }
+        match vtype {
+            // an int can go wherever a num is expected and is widened there
+            VarType::Num => matches!(self, Value::Number(_) | Value::Int(_)),
+            VarType::Int => matches!(self, Value::Int(_)),
+            VarType::Str => matches!(self, Value::StringVal(_)),
+            VarType::Bool => matches!(self, Value::Boolean(_)),
+            VarType::Fxn => matches!(self, Value::Function(_) | Value::Native(_)),
+            VarType::Array(elem) => match self {
+                Value::Array(items) => items.borrow().iter().all(|v| v.is_of_type(elem)),
+                _ => false,
+            },
+            VarType::Map(elem) => match self {
+                Value::Map(entries) => entries.borrow().iter().all(|(_, v)| v.is_of_type(elem)),
+                _ => false,
+            },
+            VarType::Struct(name) => match self {
+                Value::Instance(inst) => inst.decl.name == *name,
+                Value::Variant(v) => v.decl.name == *name,
+                _ => false,
+            },
+            VarType::Var => true,
+            VarType::Nullable(_) => false,
+        }
+    }
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "num",
+            Value::Int(_) => "int",
+            Value::StringVal(_) => "str",
+            Value::Boolean(_) => "bool",
+            Value::Function(_) | Value::Native(_) => "fxn",
+            Value::Reference(_, _) => "ref",
+            Value::Array(_) => "array",
+            Value::Map(_) => "map",
+            Value::Null => "null",
+            Value::Module(_) => "module",
+            Value::Struct(_) => "struct",
+            Value::Instance(_) => "instance",
+            Value::Enum(_) => "enum",
+            Value::Variant(_) => "variant",
+        }
+    }
+    /// The narrowest declared type this value satisfies, used to fix the type of a `var`.
+    pub fn infer_type(&self) -> VarType {
+        match self {
+            Value::Number(_) => VarType::Num,
+            Value::Int(_) => VarType::Int,
+            Value::StringVal(_) => VarType::Str,
+            Value::Boolean(_) => VarType::Bool,
+            Value::Function(_) | Value::Native(_) => VarType::Fxn,
+            Value::Array(_) => VarType::Array(Box::new(VarType::Var)),
+            Value::Map(_) => VarType::Map(Box::new(VarType::Var)),
+            Value::Reference(slot, name) => match slot.upgrade() {
+                Some(tbl) => match tbl.try_borrow() {
+                    Ok(t) => match t.map.get(name) {
+                        Some(val) => val.infer_type(),
+                        None => VarType::Var,
+                    },
+                    Err(_) => VarType::Var,
+                },
+                None => VarType::Var,
+            },
+            Value::Null => VarType::Var,
+            Value::Module(_) => VarType::Var,
+            Value::Struct(_) => VarType::Var,
+            Value::Instance(inst) => VarType::Struct(inst.decl.name.clone()),
+            Value::Enum(_) => VarType::Var,
+            Value::Variant(v) => VarType::Struct(v.decl.name.clone()),
+        }
+    }
+    /// Widens the ints in a value stored where `vtype` is declared. Arrays and maps are
+    /// widened in place, so every name sharing them still sees the same items.
+    pub fn coerce(self, vtype: &VarType) -> Value {
+        if !self.needs_widening(vtype) {
+            return self;
+        }
+        match (vtype, self) {
+            (VarType::Num, Value::Int(n)) => Value::Number(n as f64),
+            (VarType::Nullable(inner), val) => val.coerce(inner),
+            (VarType::Array(elem), Value::Array(items)) => {
+                for item in items.borrow_mut().iter_mut() {
+                    *item = item.clone().coerce(elem);
+                }
+                Value::Array(items)
+            }
+            (VarType::Map(elem), Value::Map(entries)) => {
+                for (_, val) in entries.borrow_mut().iter_mut() {
+                    *val = val.clone().coerce(elem);
+                }
+                Value::Map(entries)
+            }
+            (_, val) => val,
+        }
+    }
+    fn needs_widening(&self, vtype: &VarType) -> bool {
+        match (vtype, self) {
+            (VarType::Num, Value::Int(_)) => true,
+            (VarType::Nullable(inner), val) => val.needs_widening(inner),
+            (VarType::Array(elem), Value::Array(items)) => {
+                items.borrow().iter().any(|v| v.needs_widening(elem))
+            }
+            (VarType::Map(elem), Value::Map(entries)) => {
+                entries.borrow().iter().any(|(_, v)| v.needs_widening(elem))
+            }
+            _ => false,
+        }
+    }
+    /// The value of a `num` or `int` as a float; anything else is `NaN`.
+    pub fn as_number(&self) -> f64 {
+        match self {
+            Value::Number(n) => *n,
+            Value::Int(n) => *n as f64,
+            _ => f64::NAN,
+        }
+    }
+    /// Follows a reference to the value stored in the slot it points to.
+    pub fn deref(&self, index: &usize, lines: &[usize]) -> Result<Value, RuntimeError> {
+        self.deref_level(index, lines, 0)
+    }
+    fn deref_level(
+        &self,
+        index: &usize,
+        lines: &[usize],
+        level: usize,
+    ) -> Result<Value, RuntimeError> {
+        match self {
+            Value::Reference(slot, name) => {
+                let target = match slot.upgrade() {
+                    Some(tbl) => tbl.borrow().map.get(name).cloned(),
+                    None => None,
+                };
+                match target {
+                    Some(val) => {
+                        if level < 256 {
+                            return val.deref_level(index, lines, level + 1);
+                        }
+                        error("Reference chain is too deep", index, lines)
+                    }
+                    None => error(
+                        &format!("Reference to '{}' is no longer valid", name),
+                        index,
+                        lines,
+                    ),
+                }
+            }
+            _ => Ok(self.clone()),
+        }
+    }
+    /// `true` unless this is a reference whose scope has been dropped.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Value::Reference(slot, name) => match slot.upgrade() {
+                Some(tbl) => tbl.borrow().map.contains_key(name),
+                None => false,
+            },
+            _ => true,
         }
     }
 }
@@ -53,127 +331,621 @@ impl Expression {
     pub fn evaluate(
         &self,
         table: Rc<RefCell<SymbolTable>>,
-        debug_lines: &Vec<usize>,
-    ) -> Result<Value, String> {
+        debug_lines: &[usize],
+    ) -> Result<Value, RuntimeError> {
         match self {
             Expression::SpecialSymbol(sym, _sindx) => {
                 table
                     .borrow_mut()
-                    .get_from_symbol(&sym, _sindx, debug_lines, 0)
+                    .get_from_symbol(sym, _sindx, debug_lines, 0)
             }
             Expression::Literal(val_, _lindx) => Result::Ok(val_.clone()),
             Expression::Unary(sign, expr) => {
-                let r_: Result<Value, String> = expr.evaluate(table, debug_lines);
+                let r_: Result<Value, RuntimeError> = expr.evaluate(table, debug_lines);
                 match r_ {
-                    Ok(_v_) => unary_signing(&_v_, &sign, debug_lines),
+                    Ok(_v_) => unary_signing(&_v_, sign, debug_lines),
                     _ => r_,
                 }
             }
             Expression::Binary(left, sign, right) => {
-                let l_r: Result<Value, String> = left.evaluate(Rc::clone(&table), debug_lines);
-                let r_r: Result<Value, String> = right.evaluate(table, debug_lines);
-
-                match l_r {
-                    Ok(_l_) => match r_r {
-                        Ok(_r_) => binary_operation(&_l_, &sign, &_r_, debug_lines),
-                        _ => r_r,
-                    },
-                    _ => l_r,
+                let l = left.evaluate(Rc::clone(&table), debug_lines)?;
+                // `&` and `|` skip the right side once the left one decides the result
+                match (&sign.ttype, &l) {
+                    (TokenType::And, Value::Boolean(false)) => return Ok(l),
+                    (TokenType::Or, Value::Boolean(true)) => return Ok(l),
+                    _ => {}
                 }
+                let r = right.evaluate(table, debug_lines)?;
+                binary_operation(&l, sign, &r, debug_lines)
             }
             Expression::Group(g) => g.evaluate(table, debug_lines),
             Expression::Variable(name, index) => {
-                table
+                let val = table
                     .borrow_mut()
-                    .get_from_symbol(name, index, debug_lines, 0)
+                    .get_from_symbol(name, index, debug_lines, 0)?;
+                val.deref(index, debug_lines)
             }
             Expression::Assignment(name, rhs, s_idx) => {
                 let val = rhs.evaluate(Rc::clone(&table), debug_lines)?;
-                table
-                    .borrow_mut()
-                    .set_var_val(name, val, s_idx, debug_lines, 0)
+                assign_variable(table, name, val, s_idx, debug_lines, 0)
             }
+            Expression::Call(callee, args, c_idx) => {
+                let fx = match callee.as_ref() {
+                    Expression::Get(obj, name, g_idx) => {
+                        match obj.evaluate(Rc::clone(&table), debug_lines)? {
+                            // `Enum.Variant(...)` builds a variant rather than calling a function
+                            Value::Enum(decl) => {
+                                let mut payload: Vec<Value> = Vec::<Value>::new();
+                                for arg in args {
+                                    payload.push(arg.evaluate(Rc::clone(&table), debug_lines)?);
+                                }
+                                return EnumDecl::construct(&decl, name, payload, c_idx, debug_lines);
+                            }
+                            owner => member(owner, name, g_idx, debug_lines)?,
+                        }
+                    }
+                    _ => callee.evaluate(Rc::clone(&table), debug_lines)?,
+                };
+                let mut arg_vals: Vec<Value> = Vec::<Value>::new();
+                for arg in args {
+                    arg_vals.push(arg.evaluate(Rc::clone(&table), debug_lines)?);
+                }
+
+                match fx {
+                    Value::Function(f) => f.call(arg_vals, c_idx, debug_lines),
+                    Value::Native(f) => f.call(arg_vals, c_idx, debug_lines),
+                    _ => error("Can only call functions", c_idx, debug_lines),
+                }
+            }
+            Expression::Reference(name, r_idx) => {
+                match SymbolTable::resolve(&table, name, 0) {
+                    Some(owner) => Ok(Value::Reference(Rc::downgrade(&owner), name.clone())),
+                    None => error(
+                        &format!("Symbol '{}' does not exist!", name),
+                        r_idx,
+                        debug_lines,
+                    ),
+                }
+            }
+            Expression::Query(expr, _q_idx) => {
+                Ok(Value::Boolean(query(expr, table, debug_lines)?))
+            }
+            Expression::ArrayLiteral(items, _a_idx) => {
+                let mut vals: Vec<Value> = Vec::<Value>::new();
+                for item in items {
+                    vals.push(item.evaluate(Rc::clone(&table), debug_lines)?);
+                }
+                Ok(Value::Array(Rc::new(RefCell::new(vals))))
+            }
+            Expression::MapLiteral(entries, m_idx) => {
+                let map = Value::Map(Rc::new(RefCell::new(Vec::<(String, Value)>::new())));
+                for (key, val) in entries {
+                    let k = key.evaluate(Rc::clone(&table), debug_lines)?;
+                    let v = val.evaluate(Rc::clone(&table), debug_lines)?;
+                    set_index(&map, &k, v, m_idx, debug_lines)?;
+                }
+                Ok(map)
+            }
+            Expression::Index(arr, idx, i_idx) => {
+                let collection = arr.evaluate(Rc::clone(&table), debug_lines)?;
+                let position = idx.evaluate(table, debug_lines)?;
+                let val = index_value(&collection, &position, i_idx, debug_lines)?;
+                val.deref(i_idx, debug_lines)
+            }
+            Expression::IndexAssignment(arr, idx, rhs, i_idx) => {
+                let collection = arr.evaluate(Rc::clone(&table), debug_lines)?;
+                let position = idx.evaluate(Rc::clone(&table), debug_lines)?;
+                let val = rhs.evaluate(table, debug_lines)?;
+                set_index(&collection, &position, val, i_idx, debug_lines)
+            }
+            Expression::CompoundAssignment(target, op, rhs) => match target.as_ref() {
+                Expression::Variable(name, v_idx) => {
+                    let val = table
+                        .borrow_mut()
+                        .get_from_symbol(name, v_idx, debug_lines, 0)?;
+                    let current = val.deref(v_idx, debug_lines)?;
+                    let operand = rhs.evaluate(Rc::clone(&table), debug_lines)?;
+                    let val = binary_operation(&current, op, &operand, debug_lines)?;
+                    assign_variable(table, name, val, v_idx, debug_lines, 0)
+                }
+                Expression::Index(arr, idx, i_idx) => {
+                    let collection = arr.evaluate(Rc::clone(&table), debug_lines)?;
+                    let position = idx.evaluate(Rc::clone(&table), debug_lines)?;
+                    let current = index_value(&collection, &position, i_idx, debug_lines)?
+                        .deref(i_idx, debug_lines)?;
+                    let operand = rhs.evaluate(table, debug_lines)?;
+                    let val = binary_operation(&current, op, &operand, debug_lines)?;
+                    set_index(&collection, &position, val, i_idx, debug_lines)
+                }
+                Expression::Get(obj, name, g_idx) => match obj.evaluate(Rc::clone(&table), debug_lines)? {
+                    Value::Instance(inst) => {
+                        let current = inst.get(name, g_idx, debug_lines)?.deref(g_idx, debug_lines)?;
+                        let operand = rhs.evaluate(table, debug_lines)?;
+                        let val = binary_operation(&current, op, &operand, debug_lines)?;
+                        inst.set(name, val, g_idx, debug_lines)
+                    }
+                    _ => error("Can only assign to fields of structs", g_idx, debug_lines),
+                },
+                _ => error("Invalid assignment target", &op.index, debug_lines),
+            },
+            Expression::Get(obj, name, g_idx) => {
+                let owner = obj.evaluate(table, debug_lines)?;
+                member(owner, name, g_idx, debug_lines)
+            }
+            Expression::SetField(obj, name, rhs, s_idx) => {
+                let owner = obj.evaluate(Rc::clone(&table), debug_lines)?;
+                let val = rhs.evaluate(table, debug_lines)?;
+                match owner {
+                    Value::Instance(inst) => inst.set(name, val, s_idx, debug_lines),
+                    _ => error("Can only assign to fields of structs", s_idx, debug_lines),
+                }
+            }
+            // the closure keeps the scope it was created in alive
+            Expression::Lambda(decl) => Ok(Value::Function(Rc::new(Function::new(
+                Rc::clone(decl),
+                table,
+            )))),
+            Expression::Convert(vtype, expr, c_idx) => {
+                let val = expr.evaluate(table, debug_lines)?;
+                convert(&val, vtype, c_idx, debug_lines)
+            }
+            Expression::StructLiteral(name, fields, l_idx) => {
+                let decl = match table.borrow_mut().get_from_symbol(name, l_idx, debug_lines, 0)? {
+                    Value::Struct(decl) => decl,
+                    _ => return error(&format!("'{}' is not a struct", name), l_idx, debug_lines),
+                };
+                let mut given: Vec<(String, Value)> = Vec::new();
+                for (field, expr) in fields {
+                    given.push((field.clone(), expr.evaluate(Rc::clone(&table), debug_lines)?));
+                }
+                StructDecl::construct(&decl, given, l_idx, debug_lines)
+            }
+        }
+    }
+}
+
+/// Reads `owner.name` from a module, a struct instance or an enum.
+fn member(owner: Value, name: &str, index: &usize, lines: &[usize]) -> Result<Value, RuntimeError> {
+    match owner {
+        Value::Module(m) => match m.exports.get(name) {
+            Some(val) => Ok(val.clone()),
+            None => error(
+                &format!("Module '{}' does not export '{}'", m.name, name),
+                index,
+                lines,
+            ),
+        },
+        Value::Instance(inst) => inst.get(name, index, lines)?.deref(index, lines),
+        Value::Enum(decl) => EnumDecl::unit(&decl, name, index, lines),
+        _ => error("Only modules, structs and enums have members", index, lines),
+    }
+}
+
+/// Evaluates `?expr`; an index is only valid when the collection it reads from is.
+fn query(
+    expr: &Expression,
+    table: Rc<RefCell<SymbolTable>>,
+    lines: &[usize],
+) -> Result<bool, RuntimeError> {
+    match expr {
+        Expression::Variable(name, index) => {
+            let val = table.borrow_mut().get_from_symbol(name, index, lines, 0)?;
+            Ok(val.is_valid())
         }
+        Expression::Index(arr, idx, i_idx) => {
+            if !query(arr, Rc::clone(&table), lines)? {
+                return Ok(false);
+            }
+            let collection = arr.evaluate(Rc::clone(&table), lines)?;
+            let position = idx.evaluate(table, lines)?;
+            Ok(index_value(&collection, &position, i_idx, lines)?.is_valid())
+        }
+        _ => Ok(expr.evaluate(table, lines)?.is_valid()),
+    }
+}
+
+fn array_position(
+    position: &Value,
+    length: usize,
+    index: &usize,
+    lines: &[usize],
+) -> Result<usize, RuntimeError> {
+    match position {
+        Value::Int(n) => {
+            if *n < 0 || *n as usize >= length {
+                return error(
+                    &format!("Index {} out of bounds for length {}", n, length),
+                    index,
+                    lines,
+                );
+            }
+            Ok(*n as usize)
+        }
+        Value::Number(n) => {
+            if n.fract() != 0.0 || *n < 0.0 {
+                return error("Index must be a whole number", index, lines);
+            }
+            if *n as usize >= length {
+                return error(
+                    &format!("Index {} out of bounds for length {}", n, length),
+                    index,
+                    lines,
+                );
+            }
+            Ok(*n as usize)
+        }
+        _ => error("Index must be an 'int' or a 'num'", index, lines),
     }
 }
 
+/// Reads an element of an array, or a character of a string, without dereferencing it.
+fn index_value(
+    collection: &Value,
+    position: &Value,
+    index: &usize,
+    lines: &[usize],
+) -> Result<Value, RuntimeError> {
+    match collection {
+        Value::Array(items) => {
+            let items = items.borrow();
+            let at = array_position(position, items.len(), index, lines)?;
+            Ok(items[at].clone())
+        }
+        Value::StringVal(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            let at = array_position(position, chars.len(), index, lines)?;
+            Ok(Value::StringVal(chars[at].to_string()))
+        }
+        Value::Map(entries) => {
+            let key = map_key(position, index, lines)?;
+            match entries.borrow().iter().find(|(k, _)| *k == key) {
+                Some((_, val)) => Ok(val.clone()),
+                None => error(&format!("Key '{}' is not in the map", key), index, lines),
+            }
+        }
+        _ => error("Can only index into arrays, maps and strings", index, lines),
+    }
+}
+
+/// Replaces an element of an array, keeping the element's type.
+fn set_index(
+    collection: &Value,
+    position: &Value,
+    val: Value,
+    index: &usize,
+    lines: &[usize],
+) -> Result<Value, RuntimeError> {
+    match collection {
+        Value::Array(items) => {
+            let at = array_position(position, items.borrow().len(), index, lines)?;
+            let mut items = items.borrow_mut();
+            let val = widen_like(&items[at], val);
+            let replaces_null = matches!(items[at], Value::Null) || matches!(val, Value::Null);
+            if !replaces_null && std::mem::discriminant(&items[at]) != std::mem::discriminant(&val)
+            {
+                return error(
+                    "Cannot assign different typed value to array element",
+                    index,
+                    lines,
+                );
+            }
+            items[at] = val.clone();
+            Ok(val)
+        }
+        Value::Map(entries) => {
+            let key = map_key(position, index, lines)?;
+            let mut entries = entries.borrow_mut();
+            // new keys must match the type of the entries already there
+            let at = entries.iter().position(|(k, _)| *k == key);
+            let existing = match at {
+                Some(i) => Some(&entries[i].1),
+                None => entries.iter().map(|(_, v)| v).find(|v| !matches!(v, Value::Null)),
+            };
+            let val = match existing {
+                Some(old) => widen_like(old, val),
+                None => val,
+            };
+            if let Some(old) = existing {
+                let replaces_null = matches!(old, Value::Null) || matches!(val, Value::Null);
+                if !replaces_null && std::mem::discriminant(old) != std::mem::discriminant(&val) {
+                    return error("Cannot assign different typed value to map entry", index, lines);
+                }
+            }
+            match at {
+                Some(i) => entries[i].1 = val.clone(),
+                None => entries.push((key, val.clone())),
+            }
+            Ok(val)
+        }
+        _ => error("Can only assign to an index of an array or a map", index, lines),
+    }
+}
+
+/// An int stored over a num becomes a num, so number arrays and maps stay one type.
+fn widen_like(old: &Value, val: Value) -> Value {
+    match (old, val) {
+        (Value::Number(_), Value::Int(n)) => Value::Number(n as f64),
+        (_, val) => val,
+    }
+}
+
+/// `int(x)` truncates towards zero and `num(x)` widens; both also parse strings.
+fn convert(
+    val: &Value,
+    vtype: &VarType,
+    index: &usize,
+    lines: &[usize],
+) -> Result<Value, RuntimeError> {
+    let converted = match (vtype, val) {
+        (VarType::Int, Value::Int(n)) => Some(Value::Int(*n)),
+        (VarType::Int, Value::Number(n)) => {
+            // the bounds are the nearest floats inside the i64 range
+            if n.is_finite() && *n >= -9.223372036854775e18 && *n <= 9.223372036854775e18 {
+                Some(Value::Int(n.trunc() as i64))
+            } else {
+                None
+            }
+        }
+        (VarType::Int, Value::StringVal(s)) => s.trim().parse::<i64>().ok().map(Value::Int),
+        (_, Value::Int(n)) => Some(Value::Number(*n as f64)),
+        (_, Value::Number(n)) => Some(Value::Number(*n)),
+        (_, Value::StringVal(s)) => s.trim().parse::<f64>().ok().map(Value::Number),
+        _ => None,
+    };
+    match converted {
+        Some(v) => Ok(v),
+        None => error(
+            &format!("Cannot convert {:?} to {}", val, vtype),
+            index,
+            lines,
+        ),
+    }
+}
+
+/// Arithmetic and comparisons between two ints, which stay exact. `/` gives a `num`.
+fn int_operation(
+    left: i64,
+    operator: &Token,
+    right: i64,
+    lines: &[usize],
+) -> Option<Result<Value, RuntimeError>> {
+    let checked = match operator.ttype {
+        TokenType::Plus => left.checked_add(right),
+        TokenType::Minus => left.checked_sub(right),
+        TokenType::Star => left.checked_mul(right),
+        TokenType::Mod => {
+            if right == 0 {
+                return Some(error("Division by zero", &operator.index, lines));
+            }
+            left.checked_rem(right)
+        }
+        TokenType::Slash => return Some(Ok(Value::Number(left as f64 / right as f64))),
+        TokenType::Greater => return Some(Ok(Value::Boolean(left > right))),
+        TokenType::GreaterEquals => return Some(Ok(Value::Boolean(left >= right))),
+        TokenType::Lesser => return Some(Ok(Value::Boolean(left < right))),
+        TokenType::LesserEquals => return Some(Ok(Value::Boolean(left <= right))),
+        TokenType::Equality => return Some(Ok(Value::Boolean(left == right))),
+        TokenType::BangEquals => return Some(Ok(Value::Boolean(left != right))),
+        _ => return None,
+    };
+    match checked {
+        Some(n) => Some(Ok(Value::Int(n))),
+        None => Some(error("Integer overflow", &operator.index, lines)),
+    }
+}
+
+/// The value of a bitwise operand, which has to be an `int` or a `num` without a fraction.
+fn whole_number(val: &Value, operator: &Token, lines: &[usize]) -> Result<i64, RuntimeError> {
+    match val {
+        Value::Int(n) => Ok(*n),
+        Value::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => Ok(*n as i64),
+        Value::Number(n) => error(
+            &format!("Bitwise operators need whole numbers, but got {}", n),
+            &operator.index,
+            lines,
+        ),
+        _ => error(
+            "Bitwise operators only work on an 'int' or a 'num'",
+            &operator.index,
+            lines,
+        ),
+    }
+}
+
+/// `band`, `bor`, `bxor`, `<<` and `>>`, which always give an `int`.
+fn bitwise_operation(
+    left: i64,
+    operator: &Token,
+    right: i64,
+    lines: &[usize],
+) -> Result<Value, RuntimeError> {
+    let shift_by = |amount: i64| match u32::try_from(amount) {
+        Ok(n) if n < 64 => Ok(n),
+        _ => error(
+            &format!("Cannot shift by {}, it has to be from 0 to 63", amount),
+            &operator.index,
+            lines,
+        ),
+    };
+    match operator.ttype {
+        TokenType::BitAnd => Ok(Value::Int(left & right)),
+        TokenType::BitOr => Ok(Value::Int(left | right)),
+        TokenType::BitXor => Ok(Value::Int(left ^ right)),
+        TokenType::ShiftLeft => Ok(Value::Int(left << shift_by(right)?)),
+        TokenType::ShiftRight => Ok(Value::Int(left >> shift_by(right)?)),
+        _ => opp_undef(operator, lines),
+    }
+}
+
+/// `**` between two ints stays an `int`, anything else with a `num` gives a `num`.
+fn power(
+    left: &Value,
+    operator: &Token,
+    right: &Value,
+    lines: &[usize],
+) -> Result<Value, RuntimeError> {
+    match (left, right) {
+        (Value::Int(_), Value::Int(r)) if *r < 0 => error(
+            "An 'int' cannot be raised to a negative power, use num() on it first",
+            &operator.index,
+            lines,
+        ),
+        (Value::Int(l), Value::Int(r)) => {
+            match u32::try_from(*r).ok().and_then(|exp| l.checked_pow(exp)) {
+                Some(n) => Ok(Value::Int(n)),
+                None => error("Integer overflow", &operator.index, lines),
+            }
+        }
+        (Value::Int(_) | Value::Number(_), Value::Int(_) | Value::Number(_)) => {
+            Ok(Value::Number(left.as_number().powf(right.as_number())))
+        }
+        _ => opp_undef(operator, lines),
+    }
+}
+
+fn map_key(position: &Value, index: &usize, lines: &[usize]) -> Result<String, RuntimeError> {
+    match position {
+        Value::StringVal(s) => Ok(s.clone()),
+        _ => error("Map keys must be strings", index, lines),
+    }
+}
+
+/// Assigns to a variable, writing through it when it holds a reference.
+/// Assigning another reference re-points the variable instead.
+fn assign_variable(
+    table: Rc<RefCell<SymbolTable>>,
+    name: &str,
+    val: Value,
+    index: &usize,
+    lines: &[usize],
+    level: usize,
+) -> Result<Value, RuntimeError> {
+    let current = table.borrow_mut().get_from_symbol(name, index, lines, 0)?;
+    if let Value::Reference(slot, target) = current {
+        if !matches!(val, Value::Reference(_, _)) && level < 256 {
+            return match slot.upgrade() {
+                Some(tbl) => assign_variable(tbl, &target, val, index, lines, level + 1),
+                None => error(
+                    &format!("Reference to '{}' is no longer valid", target),
+                    index,
+                    lines,
+                ),
+            };
+        }
+    }
+
+    table.borrow_mut().set_var_val(name, val, index, lines, 0)
+}
+
 fn binary_operation(
     left: &Value,
     operator: &Token,
     right: &Value,
-    lines: &Vec<usize>,
-) -> Result<Value, String> {
+    lines: &[usize],
+) -> Result<Value, RuntimeError> {
+    match operator.ttype {
+        TokenType::Equality | TokenType::BangEquals
+            if matches!(left, Value::Null) || matches!(right, Value::Null) =>
+        {
+            let both = matches!(left, Value::Null) && matches!(right, Value::Null);
+            if operator.ttype == TokenType::Equality {
+                return Ok(Value::Boolean(both));
+            }
+            return Ok(Value::Boolean(!both));
+        }
+        TokenType::Equality | TokenType::BangEquals => {
+            if let (Value::Variant(l), Value::Variant(r)) = (left, right) {
+                let same = same_variant(l, r, operator, lines)?;
+                return Ok(Value::Boolean(same == (operator.ttype == TokenType::Equality)));
+            }
+        }
+        TokenType::BitAnd
+        | TokenType::BitOr
+        | TokenType::BitXor
+        | TokenType::ShiftLeft
+        | TokenType::ShiftRight => {
+            let l = whole_number(left, operator, lines)?;
+            let r = whole_number(right, operator, lines)?;
+            return bitwise_operation(l, operator, r, lines);
+        }
+        TokenType::Power => return power(left, operator, right, lines),
+        _ => {}
+    }
+
+    // ints stay exact among themselves and widen to a num next to one
+    match (left, right) {
+        (Value::Int(l), Value::Int(r)) => {
+            if let Some(res) = int_operation(*l, operator, *r, lines) {
+                return res;
+            }
+        }
+        (Value::Int(l), Value::Number(_)) => {
+            return binary_operation(&Value::Number(*l as f64), operator, right, lines)
+        }
+        (Value::Number(_), Value::Int(r)) => {
+            return binary_operation(left, operator, &Value::Number(*r as f64), lines)
+        }
+        (Value::Int(l), Value::StringVal(rs)) if operator.ttype == TokenType::Plus => {
+            return Ok(Value::StringVal(l.to_string() + rs))
+        }
+        _ => {}
+    }
+
     match operator.ttype {
         TokenType::Plus => match left {
             Value::Number(ln) => match right {
-                Value::Number(rn) => {
-                    return Result::Ok(Value::Number(rn + ln));
-                }
-                Value::StringVal(rs) => {
-                    return Result::Ok(Value::StringVal(ln.to_string() + rs));
-                }
-                _ => return opp_undef(operator, lines),
+                Value::Number(rn) => Result::Ok(Value::Number(rn + ln)),
+                Value::StringVal(rs) => Result::Ok(Value::StringVal(ln.to_string() + rs)),
+                _ => opp_undef(operator, lines),
             },
             Value::StringVal(ls) => match right {
-                Value::Number(rn) => {
-                    return Result::Ok(Value::StringVal(ls.to_owned() + &rn.to_string()));
-                }
-                Value::StringVal(rs) => {
-                    return Result::Ok(Value::StringVal(ls.to_string() + rs));
-                }
-                Value::Boolean(rb) => {
-                    return Result::Ok(Value::StringVal(ls.to_owned() + &rb.to_string()));
-                }
+                Value::Number(rn) => Result::Ok(Value::StringVal(ls.to_owned() + &rn.to_string())),
+                Value::StringVal(rs) => Result::Ok(Value::StringVal(ls.to_string() + rs)),
+                Value::Boolean(rb) => Result::Ok(Value::StringVal(ls.to_owned() + &rb.to_string())),
+                // anything else is added the way `print` shows it
+                other => Result::Ok(Value::StringVal(ls.to_owned() + &other.to_string())),
             },
-            _ => return opp_undef(operator, lines),
+            _ => opp_undef(operator, lines),
         },
         TokenType::Minus => match left {
             Value::Number(ln) => match right {
-                Value::Number(rn) => {
-                    return Result::Ok(Value::Number(ln - rn));
-                }
-                _ => return opp_undef(operator, lines),
+                Value::Number(rn) => Result::Ok(Value::Number(ln - rn)),
+                _ => opp_undef(operator, lines),
             },
-            _ => return opp_undef(operator, lines),
+            _ => opp_undef(operator, lines),
         },
         TokenType::Star => match left {
             Value::Number(ln) => match right {
-                Value::Number(rn) => {
-                    return Result::Ok(Value::Number(ln * rn));
-                }
-                _ => return opp_undef(operator, lines),
+                Value::Number(rn) => Result::Ok(Value::Number(ln * rn)),
+                _ => opp_undef(operator, lines),
             },
             Value::StringVal(sl) => match right {
-                Value::Number(rn) => {
-                    let mut rep = *rn;
+                Value::Number(_) | Value::Int(_) => {
+                    let mut rep = right.as_number();
                     let mut mstr = "".to_owned();
                     while rep > 0.0 {
                         mstr.push_str(sl);
-                        rep = rep - 1.0;
+                        rep -= 1.0;
                     }
-                    return Result::Ok(Value::StringVal(mstr));
+                    Result::Ok(Value::StringVal(mstr))
                 }
-                _ => return opp_undef(operator, lines),
+                _ => opp_undef(operator, lines),
             },
-            _ => return opp_undef(operator, lines),
+            _ => opp_undef(operator, lines),
         },
         TokenType::Slash => match left {
             Value::Number(ln) => match right {
-                Value::Number(rn) => {
-                    return Result::Ok(Value::Number(ln / rn));
-                }
-                _ => return opp_undef(operator, lines),
+                Value::Number(rn) => Result::Ok(Value::Number(ln / rn)),
+                _ => opp_undef(operator, lines),
             },
-            _ => return opp_undef(operator, lines),
+            _ => opp_undef(operator, lines),
         },
         TokenType::Mod => match left {
             Value::Number(ln) => match right {
-                Value::Number(rn) => {
-                    return Result::Ok(Value::Number(ln % rn));
-                }
-                _ => return opp_undef(operator, lines),
+                Value::Number(rn) => Result::Ok(Value::Number(ln % rn)),
+                _ => opp_undef(operator, lines),
             },
-            _ => return opp_undef(operator, lines),
+            _ => opp_undef(operator, lines),
         },
 
         TokenType::And => match left {
@@ -204,6 +976,7 @@ fn binary_operation(
                 Value::StringVal(rs) => Result::Ok(Value::Boolean(*ls == *rs)),
                 _ => opp_undef(operator, lines),
             },
+            _ => opp_undef(operator, lines),
         },
         TokenType::BangEquals => match left {
             Value::Boolean(lb) => match right {
@@ -218,6 +991,7 @@ fn binary_operation(
                 Value::StringVal(rs) => Result::Ok(Value::Boolean(*ls != *rs)),
                 _ => opp_undef(operator, lines),
             },
+            _ => opp_undef(operator, lines),
         },
 
         TokenType::Greater => match left {
@@ -230,6 +1004,7 @@ fn binary_operation(
                 Value::StringVal(rs) => Result::Ok(Value::Boolean(*ls > *rs)),
                 _ => opp_undef(operator, lines),
             },
+            _ => opp_undef(operator, lines),
         },
         TokenType::GreaterEquals => match left {
             Value::Boolean(_lb) => opp_undef(operator, lines),
@@ -241,6 +1016,7 @@ fn binary_operation(
                 Value::StringVal(rs) => Result::Ok(Value::Boolean(*ls >= *rs)),
                 _ => opp_undef(operator, lines),
             },
+            _ => opp_undef(operator, lines),
         },
         TokenType::Lesser => match left {
             Value::Boolean(_lb) => opp_undef(operator, lines),
@@ -252,6 +1028,7 @@ fn binary_operation(
                 Value::StringVal(rs) => Result::Ok(Value::Boolean(*ls < *rs)),
                 _ => opp_undef(operator, lines),
             },
+            _ => opp_undef(operator, lines),
         },
         TokenType::LesserEquals => match left {
             Value::Boolean(_lb) => opp_undef(operator, lines),
@@ -263,30 +1040,64 @@ fn binary_operation(
                 Value::StringVal(rs) => Result::Ok(Value::Boolean(*ls <= *rs)),
                 _ => opp_undef(operator, lines),
             },
+            _ => opp_undef(operator, lines),
         },
 
         _ => opp_undef(operator, lines),
     }
 }
-fn opp_undef(operator: &Token, lines: &Vec<usize>) -> Result<Value, String> {
-    let l = get_line_from_index(lines, &operator.index);
-    let c = get_col(&operator.index, lines);
-    return Result::Err(format!("operation is not defined! at line {}, {}", l, c));
+/// Variants are equal when they are the same variant of the same enum with equal values.
+fn same_variant(
+    left: &EnumValue,
+    right: &EnumValue,
+    operator: &Token,
+    lines: &[usize],
+) -> Result<bool, RuntimeError> {
+    if left.decl.name != right.decl.name || left.tag != right.tag {
+        return Ok(false);
+    }
+    let equals = Token {
+        index: operator.index,
+        ttype: TokenType::Equality,
+    };
+    for (l, r) in left.payload.iter().zip(right.payload.iter()) {
+        if !boolify(&binary_operation(l, &equals, r, lines)?) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+fn opp_undef(operator: &Token, lines: &[usize]) -> Result<Value, RuntimeError> {
+    error("operation is not defined!", &operator.index, lines)
 }
 pub fn boolify(val: &Value) -> bool {
     match val {
         Value::Boolean(b) => *b,
         Value::Number(n) => *n > 0.0,
+        Value::Int(n) => *n > 0,
         Value::StringVal(s) => !s.is_empty(),
+        Value::Function(_) | Value::Native(_) => true,
+        Value::Reference(_, _) => val.is_valid(),
+        Value::Array(items) => !items.borrow().is_empty(),
+        Value::Map(entries) => !entries.borrow().is_empty(),
+        Value::Null => false,
+        Value::Module(_) => true,
+        Value::Struct(_) | Value::Instance(_) => true,
+        Value::Enum(_) | Value::Variant(_) => true,
     }
 }
-fn unary_signing(val: &Value, sign: &Token, lines: &Vec<usize>) -> Result<Value, String> {
+fn unary_signing(val: &Value, sign: &Token, lines: &[usize]) -> Result<Value, RuntimeError> {
     match sign.ttype {
         TokenType::Bang => Ok(Value::Boolean(!boolify(val))),
+        TokenType::Tilde => Ok(Value::Int(!whole_number(val, sign, lines)?)),
         TokenType::Minus => match val {
             Value::Number(n) => Result::Ok(Value::Number(-n)),
+            Value::Int(n) => match n.checked_neg() {
+                Some(neg) => Ok(Value::Int(neg)),
+                None => error("Integer overflow", &sign.index, lines),
+            },
             _ => error(
-                "Cannot use '-' on anything other than a 'num'",
+                "Cannot use '-' on anything other than a 'num' or an 'int'",
                 &sign.index,
                 lines,
             ),
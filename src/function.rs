@@ -0,0 +1,153 @@
+use crate::{
+    badger_debug::{error, RuntimeError},
+    expression::Value,
+    statement::{Flow, Statement},
+    symbol_table::SymbolTable,
+    var_type::VarType,
+};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// How deep calls may nest before the script is stopped with an error instead of overflowing
+/// the stack.
+pub const MAX_DEPTH: usize = 1000;
+
+thread_local! {
+    // the number of calls running right now
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+#[derive(Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub ptype: VarType,
+    pub index: usize,
+}
+
+pub struct FunctionDecl {
+    pub name: String,
+    pub params: Vec<Parameter>,
+    pub return_type: Option<VarType>,
+    pub body: Vec<Statement>,
+    pub index: usize,
+    pub lines: Rc<Vec<usize>>,
+}
+
+pub struct Function {
+    pub decl: Rc<FunctionDecl>,
+    pub closure: Rc<RefCell<SymbolTable>>,
+}
+
+impl Function {
+    pub fn new(decl: Rc<FunctionDecl>, closure: Rc<RefCell<SymbolTable>>) -> Function {
+        Function { decl, closure }
+    }
+
+    pub fn call(
+        &self,
+        args: Vec<Value>,
+        index: &usize,
+        debug_lines: &[usize],
+    ) -> Result<Value, RuntimeError> {
+        if args.len() != self.decl.params.len() {
+            return error(
+                &format!(
+                    "Function '{}' expects {} arguments but got {}",
+                    self.decl.name,
+                    self.decl.params.len(),
+                    args.len()
+                ),
+                index,
+                debug_lines,
+            );
+        }
+
+        let depth = DEPTH.with(|d| d.get());
+        if depth >= MAX_DEPTH {
+            return error(
+                &format!(
+                    "Calls nest deeper than {} in '{}'",
+                    MAX_DEPTH, self.decl.name
+                ),
+                index,
+                debug_lines,
+            );
+        }
+        DEPTH.with(|d| d.set(depth + 1));
+        let result = self.run(args, index, debug_lines);
+        DEPTH.with(|d| d.set(depth));
+        result
+    }
+
+    fn run(
+        &self,
+        args: Vec<Value>,
+        index: &usize,
+        debug_lines: &[usize],
+    ) -> Result<Value, RuntimeError> {
+        let mut table = SymbolTable::new(Some(Rc::clone(&self.closure)));
+        for (param, arg) in self.decl.params.iter().zip(args) {
+            if !arg.is_of_type(&param.ptype) {
+                return error(
+                    &format!(
+                        "Argument '{}' of function '{}' is not of expected type ({})",
+                        param.name,
+                        self.decl.name,
+                        param.ptype
+                    ),
+                    index,
+                    debug_lines,
+                );
+            }
+            table.add_symbol(
+                &param.name,
+                arg,
+                param.ptype.clone(),
+                &param.index,
+                &self.decl.lines,
+            )?;
+        }
+
+        // the body reports errors against the lines of the file it was declared in
+        let local_table = Rc::new(RefCell::new(table));
+        for stmt in &self.decl.body {
+            if let Flow::Return(val) = stmt.accept(Rc::clone(&local_table), &self.decl.lines)? {
+                return self.check_return(val, index, debug_lines);
+            }
+        }
+
+        match &self.decl.return_type {
+            Some(_) => error(
+                &format!("Function '{}' did not return a value", self.decl.name),
+                index,
+                debug_lines,
+            ),
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn check_return(
+        &self,
+        val: Value,
+        index: &usize,
+        debug_lines: &[usize],
+    ) -> Result<Value, RuntimeError> {
+        match &self.decl.return_type {
+            Some(rtype) => {
+                if !val.is_of_type(rtype) {
+                    return error(
+                        &format!(
+                            "Function '{}' must return a value of type ({})",
+                            self.decl.name,
+                            rtype
+                        ),
+                        index,
+                        debug_lines,
+                    );
+                }
+                Ok(val.coerce(rtype))
+            }
+            None => Ok(val),
+        }
+    }
+}
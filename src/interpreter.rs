@@ -1,11 +1,21 @@
-use crate::{expression::Value, statement::Statement, symbol_table::SymbolTable};
+use crate::{
+    badger_debug::RuntimeError,
+    expression::Value,
+    module::{module_name, ModuleLoader},
+    statement::{Flow, Statement},
+    symbol_table::SymbolTable,
+    var_type::VarType,
+};
 use std::cell::RefCell;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 pub struct Interpreter<'a> {
     symbol_table: Rc<RefCell<SymbolTable>>,
     statments: &'a Vec<Option<Statement>>,
     debug_lines: &'a Vec<usize>,
+    path: PathBuf,
+    modules: Rc<RefCell<ModuleLoader>>,
 }
 
 impl<'a> Interpreter<'a> {
@@ -13,32 +23,97 @@ impl<'a> Interpreter<'a> {
         table: Rc<RefCell<SymbolTable>>,
         stmt: &'a Vec<Option<Statement>>,
         debug_lines: &'a Vec<usize>,
+        path: &Path,
+    ) -> Interpreter<'a> {
+        let modules = Rc::new(RefCell::new(ModuleLoader::new()));
+        modules.borrow_mut().begin(path);
+        Interpreter::for_module(table, stmt, debug_lines, path, modules)
+    }
+    pub fn for_module(
+        table: Rc<RefCell<SymbolTable>>,
+        stmt: &'a Vec<Option<Statement>>,
+        debug_lines: &'a Vec<usize>,
+        path: &Path,
+        modules: Rc<RefCell<ModuleLoader>>,
     ) -> Interpreter<'a> {
         Interpreter {
             symbol_table: table,
             statments: stmt,
-            debug_lines: &debug_lines,
+            debug_lines,
+            path: path.to_path_buf(),
+            modules,
         }
     }
-    pub fn interpret(&mut self) -> Result<i32, String> {
-        for stmt in self.statments.clone() {
-            match stmt {
-                Some(s) => {
-                    let r = self.execute(&s);
-                    match r {
-                        Ok(_) => {}
-                        Err(er) => return Result::Err(er),
-                    }
-                },
-                None => {
-                    
-                }
+    /// Runs the top level of the script. A top-level `return` stops it early with a value.
+    pub fn interpret(&mut self) -> Result<Option<Value>, RuntimeError> {
+        for s in self.statments.clone().into_iter().flatten() {
+            let r = self.execute(&s);
+            match r {
+                Ok(Flow::Return(val)) => return Ok(Some(val)),
+                Ok(_) => {}
+                Err(er) => return Result::Err(er),
             }
         }
 
-        return Ok(0);
+        Ok(None)
+    }
+    /// Runs the top-level declarations and then `main`, if the script defines one.
+    /// `main` may take the command-line arguments as a `str[]` and its `num` result
+    /// becomes the exit code.
+    pub fn run(&mut self, args: Vec<String>) -> Result<i32, RuntimeError> {
+        if let Some(val) = self.interpret()? {
+            return Ok(exit_code(&val));
+        }
+
+        let entry = self.symbol_table.borrow().map.get("main").cloned();
+        let main_fxn = match entry {
+            Some(Value::Function(f)) => f,
+            _ => return Ok(0),
+        };
+
+        let mut main_args: Vec<Value> = Vec::<Value>::new();
+        if main_fxn.decl.params.len() == 1 {
+            let arg_vals: Vec<Value> = args.into_iter().map(Value::StringVal).collect();
+            main_args.push(Value::Array(Rc::new(RefCell::new(arg_vals))));
+        }
+
+        let result = main_fxn.call(main_args, &main_fxn.decl.index, self.debug_lines)?;
+        Ok(exit_code(&result))
     }
-    pub fn execute(&mut self, stmt: &Statement) -> Result<Value, String> {
-        stmt.accept(Rc::clone(&self.symbol_table), &self.debug_lines)
+    pub fn execute(&mut self, stmt: &Statement) -> Result<Flow, RuntimeError> {
+        match stmt {
+            Statement::Import(import_path, index) => self.import(import_path, index),
+            _ => stmt.accept(Rc::clone(&self.symbol_table), self.debug_lines),
+        }
+    }
+    fn import(&mut self, import_path: &str, index: &usize) -> Result<Flow, RuntimeError> {
+        // imports are resolved relative to the file that contains them
+        let full_path = match self.path.parent() {
+            Some(dir) => dir.join(import_path),
+            None => PathBuf::from(import_path),
+        };
+        let module = ModuleLoader::load(&self.modules, &full_path, index, self.debug_lines)?;
+        let name = module_name(&full_path);
+        if let Some(Value::Module(existing)) = self.symbol_table.borrow().map.get(&name) {
+            if Rc::ptr_eq(existing, &module) {
+                return Ok(Flow::Normal);
+            }
+        }
+        self.symbol_table.borrow_mut().add_symbol(
+            &name,
+            Value::Module(module),
+            VarType::Var,
+            index,
+            self.debug_lines,
+        )?;
+        Ok(Flow::Normal)
+    }
+}
+
+fn exit_code(val: &Value) -> i32 {
+    match val {
+        Value::Number(n) => *n as i32,
+        Value::Int(n) => *n as i32,
+        _ => 0,
     }
 }
@@ -2,15 +2,22 @@ use std::env;
 use std::fs;
 
 pub mod badger_debug;
+pub mod builtins;
 pub mod compiler;
 pub mod encoder;
+pub mod enums;
 pub mod expression;
+pub mod function;
 pub mod parser;
 pub mod tokenizer;
+pub mod var_type;
 // pub  mod virtual_machine;
 pub mod interpreter;
+pub mod module;
 pub mod statement;
+pub mod structs;
 pub mod symbol_table;
+pub mod type_checker;
 use compiler::Compiler;
 use interpreter::Interpreter;
 use parser::ExprStmtParser;
@@ -20,12 +27,30 @@ use std::rc::Rc;
 use symbol_table::SymbolTable;
 use tokenizer::tokenize;
 use std::fs::File;
+use std::path::Path;
+use std::process;
+use std::thread;
 use std::io::prelude::*;
 use tokenizer::Token;
+use type_checker::TypeChecker;
+
+// calls in a script nest on the Rust stack, so the script runs on a thread with room for
+// `function::MAX_DEPTH` of them
+const STACK_SIZE: usize = 256 * 1024 * 1024;
 
 fn main() {
+    let script = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("could not start the interpreter thread");
+    if script.join().is_err() {
+        process::exit(101);
+    }
+}
+
+fn run() {
     let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
+    if args.len() < 3 {
         println!(
             "No input file specified!\nUsage : {0} (c|i) [input file]",
             args[0]
@@ -54,9 +79,15 @@ fn main() {
     }
 
     let contents =
-        fs::read_to_string(_inp_file_path).expect("Should have been able to read the file");
+        fs::read_to_string(&_inp_file_path).expect("Should have been able to read the file");
 
-    let (tokens, lines): (Vec<Token>, Vec<usize>) = tokenize(contents);
+    let (tokens, lines): (Vec<Token>, Vec<usize>) = match tokenize(contents) {
+        Ok(t) => t,
+        Err(er) => {
+            println!("Error: {}", er);
+            process::exit(1);
+        }
+    };
 
     //for tok_ in &tokens {
     //    println!(
@@ -67,31 +98,40 @@ fn main() {
     //}
     let mut parser: ExprStmtParser<'_> = ExprStmtParser::new(&tokens, &lines, 0);
 
-    let table: SymbolTable = SymbolTable::new(None);
+    let table: SymbolTable = builtins::global_table();
     let rst = parser.parse_statement();
     let stmt: Vec<Option<Statement>>;
     let mut interpreter: Interpreter<'_>;
     match rst {
         Ok(s) => {
             stmt = s;
+            let mut checker = TypeChecker::new(&stmt, &lines);
+            if let Err(errors) = checker.check() {
+                for er in &errors {
+                    println!("Error: {}", er);
+                }
+                println!("Type checking failed with {} errors", errors.len());
+                process::exit(1);
+            }
             if choice == "i" {
                 interpreter = Interpreter::new(
                     Rc::<RefCell<SymbolTable>>::new(RefCell::new(table)),
                     &stmt,
                     &lines,
+                    Path::new(&_inp_file_path),
                 );
-                let _exec_res = interpreter.interpret();
+                let script_args: Vec<String> = args[3..].to_vec();
+                let _exec_res = interpreter.run(script_args);
 
                 match _exec_res {
-                    Ok(_) => {}
-                    Err(er) => println!("Error!\n{}", er),
+                    Ok(code) => process::exit(code),
+                    Err(er) => {
+                        println!("Error!\n{}", er);
+                        process::exit(1);
+                    }
                 }
             } else if choice == "c" {
-                let mut compiler = Compiler {
-                    ir_code: Vec::<String>::new(),
-                    source: &stmt,
-                    lines: &lines,
-                };
+                let mut compiler = Compiler::new(&stmt, &lines);
                 let result = compiler.compile();
 
                 match result {
@@ -101,15 +141,12 @@ fn main() {
 
                         for ir in compiler.ir_code {
                             ir_code.push_str(&ir);
-                            ir_code.push_str("\n");
+                            ir_code.push('\n');
                         }
 
                         let file = File::create(output_file);
-                        match file {
-                            Ok(mut f) => {
-                                let _ = write!(f,"{}",ir_code);
-                            },
-                            _ => {}
+                        if let Ok(mut f) = file {
+                            let _ = write!(f, "{}", ir_code);
                         }
 
                     }
@@ -121,7 +158,6 @@ fn main() {
         }
         Err(er) => {
             println!("Could not parse statements:{:?}", er);
-            return;
         }
     }
 }
@@ -0,0 +1,183 @@
+use crate::{
+    badger_debug::{error, RuntimeError},
+    builtins::global_table,
+    expression::Value,
+    interpreter::Interpreter,
+    parser::ExprStmtParser,
+    statement::Statement,
+    tokenizer::tokenize,
+    type_checker::TypeChecker,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+pub struct Module {
+    pub name: String,
+    pub exports: HashMap<String, Value>,
+}
+
+pub struct ModuleLoader {
+    // files that are being evaluated right now, outermost first
+    loading: Vec<PathBuf>,
+    loaded: HashMap<PathBuf, Rc<Module>>,
+}
+
+impl Default for ModuleLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModuleLoader {
+    pub fn new() -> ModuleLoader {
+        ModuleLoader {
+            loading: Vec::<PathBuf>::new(),
+            loaded: HashMap::new(),
+        }
+    }
+
+    /// Marks `path` as being evaluated so that importing it again is reported as a cycle.
+    pub fn begin(&mut self, path: &Path) {
+        self.loading.push(canonical(path));
+    }
+
+    pub fn finish(&mut self) {
+        self.loading.pop();
+    }
+
+    /// Evaluates the module at `path` once and returns its exports.
+    pub fn load(
+        loader: &Rc<RefCell<ModuleLoader>>,
+        path: &Path,
+        index: &usize,
+        lines: &[usize],
+    ) -> Result<Rc<Module>, RuntimeError> {
+        let full_path = canonical(path);
+        if let Some(module) = loader.borrow().loaded.get(&full_path) {
+            return Ok(Rc::clone(module));
+        }
+        if loader.borrow().loading.contains(&full_path) {
+            let mut chain: Vec<String> = Vec::<String>::new();
+            let current = loader.borrow();
+            let loading = &current.loading;
+            let start = loading.iter().position(|p| *p == full_path).unwrap_or(0);
+            for p in &loading[start..] {
+                chain.push(display_name(p));
+            }
+            chain.push(display_name(&full_path));
+            return error(
+                &format!("Import cycle detected: {}", chain.join(" -> ")),
+                index,
+                lines,
+            );
+        }
+
+        let contents = match fs::read_to_string(&full_path) {
+            Ok(c) => c,
+            Err(_) => {
+                return error(
+                    &format!("Could not read module '{}'", path.display()),
+                    index,
+                    lines,
+                )
+            }
+        };
+
+        let (tokens, module_lines) = match tokenize(contents) {
+            Ok(t) => t,
+            Err(er) => {
+                return error(
+                    &format!("Could not read module '{}': {}", path.display(), er),
+                    index,
+                    lines,
+                )
+            }
+        };
+        let mut parser = ExprStmtParser::new(&tokens, &module_lines, 0);
+        let stmt = match parser.parse_statement() {
+            Ok(s) => s,
+            Err(er) => {
+                return error(
+                    &format!("Could not parse module '{}': {}", path.display(), er),
+                    index,
+                    lines,
+                )
+            }
+        };
+
+        if let Err(errors) = TypeChecker::new(&stmt, &module_lines).check() {
+            return error(
+                &format!(
+                    "Could not type check module '{}': {}",
+                    path.display(),
+                    errors.join("; ")
+                ),
+                index,
+                lines,
+            );
+        }
+
+        let table = Rc::new(RefCell::new(global_table()));
+        let mut interpreter = Interpreter::for_module(
+            Rc::clone(&table),
+            &stmt,
+            &module_lines,
+            &full_path,
+            Rc::clone(loader),
+        );
+
+        loader.borrow_mut().begin(&full_path);
+        let result = interpreter.interpret();
+        loader.borrow_mut().finish();
+        if let Err(er) = result {
+            return Err(RuntimeError {
+                message: format!("In module '{}': {}", path.display(), er.message),
+                ..er
+            });
+        }
+
+        let mut exports: HashMap<String, Value> = HashMap::new();
+        for s in stmt.iter().flatten() {
+            if let Statement::Export(inner) = s {
+                if let Some(name) = inner.declared_name() {
+                    if let Some(val) = table.borrow().map.get(&name) {
+                        exports.insert(name, val.clone());
+                    }
+                }
+            }
+        }
+
+        let module = Rc::new(Module {
+            name: module_name(&full_path),
+            exports,
+        });
+        loader
+            .borrow_mut()
+            .loaded
+            .insert(full_path, Rc::clone(&module));
+
+        Ok(module)
+    }
+}
+
+/// The name a module is bound to in the importing file, e.g. `math` for `lib/math.bdg`.
+pub fn module_name(path: &Path) -> String {
+    match path.file_stem() {
+        Some(stem) => stem.to_string_lossy().to_string(),
+        None => path.display().to_string(),
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn display_name(path: &Path) -> String {
+    match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => path.display().to_string(),
+    }
+}
@@ -1,13 +1,28 @@
 use crate::{
+    enums::{EnumDecl, Variant},
     expression::{Expression, Value},
-    statement::Statement,
+    function::{FunctionDecl, Parameter},
+    structs::{Field, StructDecl},
+    statement::{MatchArm, Pattern, Statement},
     tokenizer::{Token, TokenType},
+    var_type::VarType,
 };
+use std::collections::HashMap;
+use std::rc::Rc;
+
+const STEP_ONLY: &str =
+    "'++' and '--' go after a variable, an item or a field, as a statement of their own";
 
 pub struct ExprStmtParser<'a> {
     current: usize,
+    loop_depth: usize,
+    block_depth: usize,
+    had_error: bool,
     lines: &'a Vec<usize>,
-    tokens: &'a Vec<Token>,
+    shared_lines: Rc<Vec<usize>>,
+    tokens: Vec<Token>,
+    // doc comments, under the position of the token that follows them
+    docs: HashMap<usize, String>,
 }
 
 impl<'a> ExprStmtParser<'a> {
@@ -16,10 +31,30 @@ impl<'a> ExprStmtParser<'a> {
         lines_: &'a Vec<usize>,
         start: usize,
     ) -> ExprStmtParser<'a> {
+        let mut tokens: Vec<Token> = Vec::<Token>::new();
+        let mut docs: HashMap<usize, String> = HashMap::new();
+        for token in tokens_ {
+            match &token.ttype {
+                TokenType::DocComment(line) => {
+                    let doc = docs.entry(tokens.len()).or_default();
+                    if !doc.is_empty() {
+                        doc.push('\n');
+                    }
+                    doc.push_str(line);
+                }
+                _ => tokens.push(token.clone()),
+            }
+        }
+
         ExprStmtParser {
-            tokens: tokens_,
+            tokens,
+            docs,
             current: start,
+            loop_depth: 0,
+            block_depth: 0,
+            had_error: false,
             lines: lines_,
+            shared_lines: Rc::new(lines_.clone()),
         }
     }
 
@@ -29,83 +64,499 @@ impl<'a> ExprStmtParser<'a> {
             stmt.push(self.declaration());
         }
 
-        return Ok(stmt);
+        if self.had_error {
+            return Err("Parsing failed with errors".to_string());
+        }
+        Ok(stmt)
     }
-    fn var_declearation(&mut self, vtype: TokenType) -> Result<Statement, String> {
+    fn var_declearation(&mut self, vtype: VarType) -> Result<Statement, String> {
         let mut name: String = "".to_string();
 
         self.consume_identifier(&mut name, "Expected variable name")?;
-        let mut init: Expression;
-
         let idx = self.peek().index - 1;
 
-        match vtype {
-            TokenType::Num => init = Expression::Literal(Value::Number(0.0), idx),
-            TokenType::Bool => init = Expression::Literal(Value::Boolean(false), idx),
-            TokenType::Str => init = Expression::Literal(Value::StringVal("".to_string()), idx),
-            TokenType::Var => init = Expression::Literal(Value::Number(0.0), idx),
-            _ => return Err("Illegal type for a variable".to_string()),
+        let default: Option<Expression> = match vtype {
+            VarType::Num => Some(Expression::Literal(Value::Number(0.0), idx)),
+            VarType::Int => Some(Expression::Literal(Value::Int(0), idx)),
+            VarType::Bool => Some(Expression::Literal(Value::Boolean(false), idx)),
+            VarType::Str => Some(Expression::Literal(Value::StringVal("".to_string()), idx)),
+            VarType::Var => Some(Expression::Literal(Value::Null, idx)),
+            VarType::Nullable(_) => Some(Expression::Literal(Value::Null, idx)),
+            VarType::Array(_) => Some(Expression::ArrayLiteral(Vec::<Expression>::new(), idx)),
+            VarType::Map(_) => Some(Expression::MapLiteral(Vec::new(), idx)),
+            // structs and functions have no empty value to start from
+            VarType::Struct(_) | VarType::Fxn => None,
+        };
+
+        let init: Expression = if self.match_tokentype(&[TokenType::Asign]) {
+            self.parse_expression()?
+        } else {
+            match default {
+                Some(d) => d,
+                None => {
+                    return Err(format!(
+                        "Variable '{}' of type {} needs a value at line {}",
+                        name,
+                        vtype,
+                        self.get_line_from_index(idx)
+                    ))
+                }
+            }
+        };
+
+        self.consume(&TokenType::EoStmt, "Expect ';' after variable declaration.")?;
+
+        Ok(Statement::VarDecl(name, init, vtype, idx))
+    }
+    /// `const TYPE? name = value;`, where the type may be left out as with `var`.
+    fn const_declaration(&mut self) -> Result<Statement, String> {
+        let vtype = match self.match_var_type() {
+            Some(base) => self.array_suffix(base)?,
+            None => VarType::Var,
+        };
+        let mut name: String = "".to_string();
+        self.consume_identifier(&mut name, "Expected constant name")?;
+        let idx = self.previous().index;
+        self.consume(&TokenType::Asign, "Expected '=' after constant name, a constant needs a value")?;
+        let init = self.parse_expression()?;
+        self.consume(&TokenType::EoStmt, "Expect ';' after constant declaration.")?;
+
+        Ok(Statement::ConstDecl(name, init, vtype, idx))
+    }
+    fn fxn_declaration(&mut self) -> Result<Statement, String> {
+        let mut return_type: Option<VarType> = None;
+        if self.match_tokentype(&[TokenType::Colon]) {
+            return_type = Some(self.type_annotation("Expected return type after ':'")?);
         }
 
-        if self.match_tokentype(&[TokenType::Asign]) {
-            init = self.parse_expression()?;
+        let mut name: String = "".to_string();
+        self.consume_identifier(&mut name, "Expected function name")?;
+        let idx = self.previous().index;
+
+        self.consume(&TokenType::OpenParent, "Expected '(' after function name")?;
+        let params = self.fxn_parameters()?;
+        let body = self.fxn_body()?;
+
+        Ok(Statement::FxnDecl(Rc::new(FunctionDecl {
+            name,
+            params,
+            return_type,
+            body,
+            index: idx,
+            lines: Rc::clone(&self.shared_lines),
+        })))
+    }
+    /// `fxn(a:num):num { ... }` in expression position, after the `fxn` keyword.
+    fn anonymous_fxn(&mut self) -> Result<Expression, String> {
+        let idx = self.previous().index;
+        self.consume(&TokenType::OpenParent, "Expected '(' after 'fxn'")?;
+        let params = self.fxn_parameters()?;
+        let mut return_type: Option<VarType> = None;
+        if self.match_tokentype(&[TokenType::Colon]) {
+            return_type = Some(self.type_annotation("Expected return type after ':'")?);
         }
+        let body = self.fxn_body()?;
 
-        self.consume(&TokenType::EoStmt, "Expect ';' after variable declaration.")?;
+        Ok(Expression::Lambda(Rc::new(FunctionDecl {
+            name: "anonymous".to_string(),
+            params,
+            return_type,
+            body,
+            index: idx,
+            lines: Rc::clone(&self.shared_lines),
+        })))
+    }
+    /// Parameters up to and including the closing `)`.
+    fn fxn_parameters(&mut self) -> Result<Vec<Parameter>, String> {
+        let mut params: Vec<Parameter> = Vec::<Parameter>::new();
+        if !self.check(&TokenType::CloseParent) {
+            loop {
+                let mut param_name: String = "".to_string();
+                self.consume_identifier(&mut param_name, "Expected parameter name")?;
+                let param_idx = self.previous().index;
+
+                let mut ptype = VarType::Var;
+                if self.match_tokentype(&[TokenType::Colon]) {
+                    ptype = self.type_annotation("Expected parameter type after ':'")?;
+                }
 
-        return Ok(Statement::VarDecl(name, init, vtype, idx));
+                params.push(Parameter {
+                    name: param_name,
+                    ptype,
+                    index: param_idx,
+                });
+
+                if !self.match_tokentype(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(&TokenType::CloseParent, "Expected ')' after parameters")?;
+        Ok(params)
     }
-    fn declaration(&mut self) -> Option<Statement> {
-        let mut vtype: Option<TokenType> = Option::None;
+    fn fxn_body(&mut self) -> Result<Vec<Statement>, String> {
+        self.consume(&TokenType::OpenBrace, "Expected '{' before function body")?;
+        let enclosing_loops = self.loop_depth;
+        self.loop_depth = 0;
+        let body = self.block();
+        self.loop_depth = enclosing_loops;
+        body
+    }
+    fn struct_declaration(&mut self) -> Result<Statement, String> {
+        let mut name: String = "".to_string();
+        self.consume_identifier(&mut name, "Expected struct name")?;
+        let idx = self.previous().index;
 
-        if self.match_tokentype(&[TokenType::Num]) {
-            vtype = Some(TokenType::Num);
-        } else if self.match_tokentype(&[TokenType::Bool]) {
-            vtype = Some(TokenType::Bool);
-        } else if self.match_tokentype(&[TokenType::Str]) {
-            vtype = Some(TokenType::Str);
-        } else if self.match_tokentype(&[TokenType::Var]) {
-            vtype = Some(TokenType::Var);
-        }
-
-        match vtype {
-            Some(typ) => {
-                let vd = self.var_declearation(typ);
-                match vd {
-                    Result::Ok(dec) => return Some(dec),
-                    Result::Err(ex) => {
-                        println!("Error: {}", ex);
-                        self.synchronize();
-                        return None;
+        self.consume(&TokenType::OpenBrace, "Expected '{' after struct name")?;
+        let mut fields: Vec<Field> = Vec::<Field>::new();
+        while !self.check(&TokenType::CloseBrace) && !self.is_at_end() {
+            let mut field_name: String = "".to_string();
+            self.consume_identifier(&mut field_name, "Expected field name")?;
+            let field_idx = self.previous().index;
+            if fields.iter().any(|f| f.name == field_name) {
+                self.error(self.previous(), "Field is declared more than once")?;
+            }
+            self.consume(&TokenType::Colon, "Expected ':' after field name")?;
+            let ftype = self.type_annotation("Expected field type after ':'")?;
+            fields.push(Field {
+                name: field_name,
+                ftype,
+                index: field_idx,
+            });
+            if !self.match_tokentype(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(&TokenType::CloseBrace, "Expected '}' after struct fields")?;
+
+        Ok(Statement::StructDecl(Rc::new(StructDecl {
+            name,
+            fields,
+            index: idx,
+        })))
+    }
+    fn enum_declaration(&mut self) -> Result<Statement, String> {
+        let mut name: String = "".to_string();
+        self.consume_identifier(&mut name, "Expected enum name")?;
+        let idx = self.previous().index;
+
+        self.consume(&TokenType::OpenBrace, "Expected '{' after enum name")?;
+        let mut variants: Vec<Variant> = Vec::<Variant>::new();
+        while !self.check(&TokenType::CloseBrace) && !self.is_at_end() {
+            let mut variant_name: String = "".to_string();
+            self.consume_identifier(&mut variant_name, "Expected variant name")?;
+            let variant_idx = self.previous().index;
+            if variants.iter().any(|v| v.name == variant_name) {
+                self.error(self.previous(), "Variant is declared more than once")?;
+            }
+            let mut payload: Vec<VarType> = Vec::<VarType>::new();
+            if self.match_tokentype(&[TokenType::OpenParent]) {
+                loop {
+                    payload.push(self.type_annotation("Expected a type for the variant's value")?);
+                    if !self.match_tokentype(&[TokenType::Comma]) {
+                        break;
                     }
                 }
+                self.consume(&TokenType::CloseParent, "Expected ')' after variant types")?;
+            }
+            variants.push(Variant {
+                name: variant_name,
+                payload,
+                index: variant_idx,
+            });
+            if !self.match_tokentype(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(&TokenType::CloseBrace, "Expected '}' after enum variants")?;
+
+        Ok(Statement::EnumDecl(Rc::new(EnumDecl {
+            name,
+            variants,
+            index: idx,
+        })))
+    }
+    fn type_annotation(&mut self, err_msg: &str) -> Result<VarType, String> {
+        let mut name: String = "".to_string();
+        if self.check_identifier(&mut name) {
+            self.advance();
+            return self.array_suffix(VarType::Struct(name));
+        }
+        if self.match_tokentype(&[
+            TokenType::Num,
+            TokenType::Int,
+            TokenType::Str,
+            TokenType::Bool,
+            TokenType::Var,
+            TokenType::Fxn,
+        ]) {
+            if let Some(base) = VarType::from_token(&self.previous().ttype) {
+                return self.array_suffix(base);
+            }
+        }
+        self.error_type(self.peek(), err_msg)
+    }
+    fn array_suffix(&mut self, base: VarType) -> Result<VarType, String> {
+        let mut vtype = base;
+        loop {
+            if self.match_tokentype(&[TokenType::OpenSquare]) {
+                self.consume(&TokenType::CloseSquare, "Expected ']' after '[' in type")?;
+                vtype = VarType::Array(Box::new(vtype));
+            } else if self.check(&TokenType::OpenBrace) && self.check_next(&TokenType::CloseBrace) {
+                // a lone `{` after a type is the body of a function
+                self.advance();
+                self.advance();
+                vtype = VarType::Map(Box::new(vtype));
+            } else if self.match_tokentype(&[TokenType::Query]) {
+                vtype = VarType::Nullable(Box::new(vtype));
+            } else {
+                return Ok(vtype);
+            }
+        }
+    }
+    fn import_declaration(&mut self) -> Result<Statement, String> {
+        let keyword = self.previous().clone();
+        if self.block_depth > 0 {
+            self.error(&keyword, "'import' is only allowed at the top level")?;
+        }
+        let mut path: String = "".to_string();
+        match self.match_string_literal() {
+            Some((p, _)) => path = p,
+            None => {
+                self.error(self.peek(), "Expected a file path after 'import'")?;
+            }
+        }
+        self.consume(&TokenType::EoStmt, "Expected ';' after import.")?;
+        Ok(Statement::Import(path, keyword.index))
+    }
+    fn check_export(&self) -> Result<&Token, String> {
+        let keyword = self.previous();
+        if self.block_depth > 0 {
+            return self.error(keyword, "'export' is only allowed at the top level");
+        }
+        // other files could reach a struct or enum but not name its type, so it stays in its file
+        if self.check(&TokenType::Struct) {
+            return self.error(keyword, "Structs cannot be exported");
+        }
+        if self.check(&TokenType::Enum) {
+            return self.error(keyword, "Enums cannot be exported");
+        }
+        let exportable = [
+            TokenType::Fxn,
+            TokenType::Num,
+            TokenType::Int,
+            TokenType::Str,
+            TokenType::Bool,
+            TokenType::Var,
+            TokenType::Const,
+        ];
+        if !exportable.iter().any(|t| self.check(t)) && !self.struct_type_ahead() {
+            return self.error(keyword, "Expected a declaration after 'export'");
+        }
+        Ok(keyword)
+    }
+    /// A declaration along with the `##` comment right before it, if there is one.
+    fn declaration(&mut self) -> Option<Statement> {
+        let doc: Option<String> = self.docs.remove(&self.current);
+        let dec: Statement = self.bare_declaration()?;
+        match (doc, dec) {
+            (Some(text), Statement::Export(inner)) => Some(Statement::Export(Box::new(
+                Statement::Doc(text, inner),
+            ))),
+            (Some(text), dec) if dec.declared_name().is_some() => {
+                Some(Statement::Doc(text, Box::new(dec)))
+            }
+            (_, dec) => Some(dec),
+        }
+    }
+    fn bare_declaration(&mut self) -> Option<Statement> {
+        if self.match_tokentype(&[TokenType::Import]) {
+            match self.import_declaration() {
+                Result::Ok(dec) => return Some(dec),
+                Result::Err(ex) => {
+                    println!("Error: {}", ex);
+                    self.had_error = true;
+                    self.synchronize();
+                    return None;
+                }
+            }
+        }
+        if self.match_tokentype(&[TokenType::Export]) {
+            if let Err(ex) = self.check_export() {
+                println!("Error: {}", ex);
+                self.had_error = true;
+                // a struct or enum is still read, so that its body is not taken for statements
+                if self.check(&TokenType::Struct) || self.check(&TokenType::Enum) {
+                    return self.declaration();
+                }
+                self.synchronize();
+                return None;
+            }
+            return self
+                .declaration()
+                .map(|dec| Statement::Export(Box::new(dec)));
+        }
+        if self.match_tokentype(&[TokenType::Struct]) {
+            match self.struct_declaration() {
+                Result::Ok(dec) => return Some(dec),
+                Result::Err(ex) => {
+                    println!("Error: {}", ex);
+                    self.had_error = true;
+                    self.synchronize();
+                    return None;
+                }
+            }
+        }
+        if self.match_tokentype(&[TokenType::Const]) {
+            match self.const_declaration() {
+                Result::Ok(dec) => return Some(dec),
+                Result::Err(ex) => {
+                    println!("Error: {}", ex);
+                    self.had_error = true;
+                    self.synchronize();
+                    return None;
+                }
+            }
+        }
+        if self.match_tokentype(&[TokenType::Enum]) {
+            match self.enum_declaration() {
+                Result::Ok(dec) => return Some(dec),
+                Result::Err(ex) => {
+                    println!("Error: {}", ex);
+                    self.had_error = true;
+                    self.synchronize();
+                    return None;
+                }
+            }
+        }
+        // `fxn(` starts an anonymous function, which is an expression
+        if self.check(&TokenType::Fxn)
+            && !self.check_next(&TokenType::OpenParent)
+            && !self.fxn_type_ahead()
+        {
+            self.advance();
+            match self.fxn_declaration() {
+                Result::Ok(dec) => return Some(dec),
+                Result::Err(ex) => {
+                    println!("Error: {}", ex);
+                    self.had_error = true;
+                    self.synchronize();
+                    return None;
+                }
+            }
+        }
+
+        let vtype: Option<VarType> = self.match_var_type();
+
+        if let Some(typ) = vtype {
+            let vd = self
+                .array_suffix(typ)
+                .and_then(|full_type| self.var_declearation(full_type));
+            match vd {
+                Result::Ok(dec) => return Some(dec),
+                Result::Err(ex) => {
+                    println!("Error: {}", ex);
+                    self.had_error = true;
+                    self.synchronize();
+                    return None;
+                }
             }
-            _ => {}
         }
 
         let st = self.statement();
 
         match st {
-            Ok(stmt) => return Some(stmt),
-            Err(_) => {
+            Ok(stmt) => Some(stmt),
+            Err(ex) => {
+                println!("Error: {}", ex);
+                self.had_error = true;
                 self.synchronize();
-                return None;
+                None
+            }
+        }
+    }
+    fn match_var_type(&mut self) -> Option<VarType> {
+        let mut vtype: Option<VarType> = Option::None;
+
+        // `int(x)` and `num(x)` are conversions, not declarations
+        if self.check_next(&TokenType::OpenParent) {
+            return vtype;
+        }
+        if self.match_tokentype(&[TokenType::Num]) {
+            vtype = Some(VarType::Num);
+        } else if self.match_tokentype(&[TokenType::Int]) {
+            vtype = Some(VarType::Int);
+        } else if self.match_tokentype(&[TokenType::Bool]) {
+            vtype = Some(VarType::Bool);
+        } else if self.match_tokentype(&[TokenType::Str]) {
+            vtype = Some(VarType::Str);
+        } else if self.match_tokentype(&[TokenType::Var]) {
+            vtype = Some(VarType::Var);
+        } else if self.fxn_type_ahead() {
+            self.advance();
+            vtype = Some(VarType::Fxn);
+        } else if self.struct_type_ahead() {
+            let mut name: String = "".to_string();
+            self.check_identifier(&mut name);
+            self.advance();
+            vtype = Some(VarType::Struct(name));
+        }
+
+        vtype
+    }
+    /// `fxn f = ...;` and `fxn[] fs` declare variables, while `fxn f(` declares a function.
+    fn fxn_type_ahead(&self) -> bool {
+        if !self.check(&TokenType::Fxn) {
+            return false;
+        }
+        match self.tokens.get(self.current + 1).map(|t| &t.ttype) {
+            Some(TokenType::OpenSquare) | Some(TokenType::Query) => true,
+            Some(TokenType::Identifier(_)) => matches!(
+                self.tokens.get(self.current + 2).map(|t| &t.ttype),
+                Some(TokenType::Asign) | Some(TokenType::EoStmt)
+            ),
+            _ => false,
+        }
+    }
+    /// `Point p`, `Point[] ps` and `Point? p` start declarations; anything else after a name is an expression.
+    fn struct_type_ahead(&self) -> bool {
+        let mut name: String = "".to_string();
+        if !self.check_identifier(&mut name) {
+            return false;
+        }
+        let mut at = self.current + 1;
+        loop {
+            match self.tokens.get(at).map(|t| &t.ttype) {
+                Some(TokenType::OpenSquare) | Some(TokenType::OpenBrace) => {
+                    let close = match self.tokens[at].ttype {
+                        TokenType::OpenSquare => TokenType::CloseSquare,
+                        _ => TokenType::CloseBrace,
+                    };
+                    match self.tokens.get(at + 1) {
+                        Some(t) if t.ttype == close => at += 2,
+                        _ => return false,
+                    }
+                }
+                Some(TokenType::Query) => at += 1,
+                Some(TokenType::Identifier(_)) => return true,
+                _ => return false,
             }
         }
     }
     fn block(&mut self) -> Result<Vec<Statement>, String> {
         let mut statements: Vec<Statement> = Vec::<Statement>::new();
+        self.block_depth += 1;
         while !self.check(&TokenType::CloseBrace) && !self.is_at_end() {
             let dec = self.declaration();
-            match dec {
-                Some(st) => statements.push(st),
-                _ => {}
+            if let Some(st) = dec {
+                statements.push(st);
             }
         }
+        self.block_depth -= 1;
 
         self.consume(&TokenType::CloseBrace, "Expected '}' after block")?;
 
-        return Ok(statements);
+        Ok(statements)
     }
     fn statement(&mut self) -> Result<Statement, String> {
         if self.match_tokentype(&[TokenType::Return]) {
@@ -120,8 +571,23 @@ impl<'a> ExprStmtParser<'a> {
         if self.match_tokentype(&[TokenType::While]) {
             return self.while_statement();
         }
+        if self.match_tokentype(&[TokenType::For]) {
+            return self.for_statement();
+        }
+        if self.match_tokentype(&[TokenType::Match]) {
+            return self.match_statement();
+        }
+        if self.match_tokentype(&[TokenType::Break, TokenType::Continue]) {
+            return self.loop_control_statement();
+        }
+        if self.match_tokentype(&[TokenType::Throw]) {
+            return self.throw_statement();
+        }
+        if self.match_tokentype(&[TokenType::Try]) {
+            return self.try_statement();
+        }
 
-        return self.expr_statement();
+        self.expr_statement()
     }
     fn if_statement(&mut self) -> Result<Statement, String> {
         self.consume(&TokenType::OpenParent, "Expect '(' after 'if'")?;
@@ -133,28 +599,261 @@ impl<'a> ExprStmtParser<'a> {
         if self.match_tokentype(&[TokenType::Else]) {
             else_branch = Some(self.statement()?);
         }
-        return Ok(Statement::IfStmt(
+        Ok(Statement::IfStmt(
             condition,
             Box::new(then),
             Box::new(else_branch),
-        ));
+        ))
     }
     fn while_statement(&mut self) -> Result<Statement, String> {
         self.consume(&TokenType::OpenParent, "Expect '(' after 'while'")?;
         let condition = self.expression()?;
         self.consume(&TokenType::CloseParent, "Expect ')' after condition")?;
-        let loop_exec = self.statement()?;
-        return Ok(Statement::WhileStmt(condition, Box::new(loop_exec)));
+        let loop_exec = self.loop_body()?;
+        Ok(Statement::WhileStmt(condition, Box::new(loop_exec)))
+    }
+    fn for_statement(&mut self) -> Result<Statement, String> {
+        self.consume(&TokenType::OpenParent, "Expect '(' after 'for'")?;
+
+        let mut name: String = "".to_string();
+        if self.check_identifier(&mut name) && self.check_next(&TokenType::In) {
+            let idx = self.advance().index;
+            self.advance();
+            let collection = self.expression()?;
+            self.consume(&TokenType::CloseParent, "Expect ')' after collection")?;
+            let body = self.loop_body()?;
+            return Ok(Statement::ForEach(name, collection, Box::new(body), idx));
+        }
+
+        let mut init: Option<Statement> = None;
+        if !self.match_tokentype(&[TokenType::EoStmt]) {
+            match self.match_var_type() {
+                Some(vtype) => {
+                    let typ = self.array_suffix(vtype)?;
+                    init = Some(self.var_declearation(typ)?);
+                }
+                None => init = Some(self.expr_statement()?),
+            }
+        }
+
+        let mut condition: Option<Expression> = None;
+        if !self.check(&TokenType::EoStmt) {
+            condition = Some(self.expression()?);
+        }
+        self.consume(&TokenType::EoStmt, "Expect ';' after loop condition")?;
+
+        let mut step: Option<Expression> = None;
+        if !self.check(&TokenType::CloseParent) {
+            step = Some(self.step_expression()?);
+        }
+        self.consume(&TokenType::CloseParent, "Expect ')' after for clauses")?;
+
+        let body = self.loop_body()?;
+        Ok(Statement::ForStmt(
+            Box::new(init),
+            condition,
+            step,
+            Box::new(body),
+        ))
+    }
+    fn match_statement(&mut self) -> Result<Statement, String> {
+        let keyword = self.previous().clone();
+        self.consume(&TokenType::OpenParent, "Expect '(' after 'match'")?;
+        let subject = self.expression()?;
+        self.consume(&TokenType::CloseParent, "Expect ')' after match value")?;
+        self.consume(&TokenType::OpenBrace, "Expect '{' before match arms")?;
+
+        let mut arms: Vec<MatchArm> = Vec::<MatchArm>::new();
+        while !self.check(&TokenType::CloseBrace) && !self.is_at_end() {
+            let mut patterns: Vec<Pattern> = vec![self.pattern()?];
+            while self.match_tokentype(&[TokenType::Or]) {
+                patterns.push(self.pattern()?);
+            }
+            self.consume(&TokenType::FatArrow, "Expect '=>' after match pattern")?;
+            let body = self.arm_body()?;
+            self.match_tokentype(&[TokenType::Comma]);
+            arms.push(MatchArm { patterns, body });
+        }
+        self.consume(&TokenType::CloseBrace, "Expect '}' after match arms")?;
+
+        Ok(Statement::Match(subject, arms, keyword.index))
+    }
+    fn pattern(&mut self) -> Result<Pattern, String> {
+        let mut name: String = "".to_string();
+        if self.check_identifier(&mut name) && name == "_" {
+            self.advance();
+            return Ok(Pattern::Wildcard);
+        }
+        if self.check_identifier(&mut name) && self.check_next(&TokenType::Period) {
+            return self.variant_pattern();
+        }
+        if let Some((s, _)) = self.match_string_literal() {
+            return Ok(Pattern::Literal(Value::StringVal(s)));
+        }
+        if self.match_tokentype(&[TokenType::True]) {
+            return Ok(Pattern::Literal(Value::Boolean(true)));
+        }
+        if self.match_tokentype(&[TokenType::False]) {
+            return Ok(Pattern::Literal(Value::Boolean(false)));
+        }
+        if self.match_tokentype(&[TokenType::Null]) {
+            return Ok(Pattern::Literal(Value::Null));
+        }
+
+        let from = self.pattern_number()?;
+        if self.match_tokentype(&[TokenType::Range]) {
+            let to = self.pattern_number()?;
+            return Ok(Pattern::Range(from.as_number(), to.as_number()));
+        }
+        Ok(Pattern::Literal(from))
+    }
+    /// `Enum.Variant`, optionally followed by names for its values.
+    fn variant_pattern(&mut self) -> Result<Pattern, String> {
+        let mut enum_name: String = "".to_string();
+        self.consume_identifier(&mut enum_name, "Expected enum name")?;
+        let idx = self.previous().index;
+        self.advance();
+        let mut variant: String = "".to_string();
+        self.consume_identifier(&mut variant, "Expected variant name after '.'")?;
+
+        let mut names: Vec<String> = Vec::<String>::new();
+        if self.match_tokentype(&[TokenType::OpenParent]) {
+            loop {
+                let mut name: String = "".to_string();
+                self.consume_identifier(&mut name, "Expected a name for the variant's value")?;
+                names.push(name);
+                if !self.match_tokentype(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+            self.consume(&TokenType::CloseParent, "Expected ')' after pattern names")?;
+        }
+        Ok(Pattern::Variant(enum_name, variant, names, idx))
+    }
+    fn pattern_number(&mut self) -> Result<Value, String> {
+        let negative = self.match_tokentype(&[TokenType::Minus]);
+        match self.match_number_literal() {
+            Some((Value::Int(n), _)) if negative => Ok(Value::Int(-n)),
+            Some((Value::Number(n), _)) if negative => Ok(Value::Number(-n)),
+            Some((n, _)) => Ok(n),
+            None => Err(format!(
+                "Expected a pattern at line {}",
+                self.get_line_from_index(self.peek().index)
+            )),
+        }
+    }
+    /// An arm runs a statement, or a bare expression that may end in `,` instead of `;`.
+    fn arm_body(&mut self) -> Result<Statement, String> {
+        let statement_start = [
+            TokenType::OpenBrace,
+            TokenType::Return,
+            TokenType::If,
+            TokenType::While,
+            TokenType::For,
+            TokenType::Match,
+            TokenType::Break,
+            TokenType::Continue,
+            TokenType::Throw,
+            TokenType::Try,
+        ];
+        if statement_start.iter().any(|t| self.check(t)) {
+            return self.statement();
+        }
+        let expr = self.step_expression()?;
+        self.match_tokentype(&[TokenType::EoStmt]);
+        Ok(Statement::Expr(expr))
+    }
+    fn loop_body(&mut self) -> Result<Statement, String> {
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        body
+    }
+    fn loop_control_statement(&mut self) -> Result<Statement, String> {
+        let keyword = self.previous().clone();
+        let is_break = keyword.ttype == TokenType::Break;
+        if self.loop_depth == 0 {
+            let name = if is_break { "break" } else { "continue" };
+            self.error(&keyword, &format!("'{}' used outside of a loop", name))?;
+        }
+        self.consume(&TokenType::EoStmt, "Expected ';' after loop control.")?;
+        if is_break {
+            return Ok(Statement::Break(keyword.index));
+        }
+        Ok(Statement::Continue(keyword.index))
+    }
+    fn throw_statement(&mut self) -> Result<Statement, String> {
+        let idx = self.previous().index;
+        let value = self.expression()?;
+        self.consume(&TokenType::EoStmt, "Expected ';' after thrown value.")?;
+        Ok(Statement::Throw(value, idx))
+    }
+    fn try_statement(&mut self) -> Result<Statement, String> {
+        let keyword = self.previous().clone();
+        self.consume(&TokenType::OpenBrace, "Expect '{' after 'try'")?;
+        let body = Statement::Block(self.block()?);
+
+        let mut handler: Option<(String, Box<Statement>)> = None;
+        if self.match_tokentype(&[TokenType::Catch]) {
+            self.consume(&TokenType::OpenParent, "Expect '(' after 'catch'")?;
+            let mut name: String = "".to_string();
+            self.consume_identifier(&mut name, "Expected error variable name")?;
+            self.consume(&TokenType::CloseParent, "Expect ')' after error variable")?;
+            self.consume(&TokenType::OpenBrace, "Expect '{' after 'catch'")?;
+            handler = Some((name, Box::new(Statement::Block(self.block()?))));
+        }
+
+        let mut finally: Option<Statement> = None;
+        if self.match_tokentype(&[TokenType::Finally]) {
+            self.consume(&TokenType::OpenBrace, "Expect '{' after 'finally'")?;
+            finally = Some(Statement::Block(self.block()?));
+        }
+
+        if handler.is_none() && finally.is_none() {
+            self.error(&keyword, "Expect 'catch' or 'finally' after 'try' block")?;
+        }
+        Ok(Statement::Try(
+            Box::new(body),
+            handler,
+            Box::new(finally),
+            keyword.index,
+        ))
     }
     fn return_statement(&mut self) -> Result<Statement, String> {
+        if self.check(&TokenType::EoStmt) {
+            let idx = self.advance().index;
+            return Ok(Statement::Return(Expression::Literal(Value::Null, idx)));
+        }
         let value = self.expression()?;
         self.consume(&TokenType::EoStmt, "Expected ';' after value.")?;
-        return Ok(Statement::Return(value));
+        Ok(Statement::Return(value))
     }
     fn expr_statement(&mut self) -> Result<Statement, String> {
-        let value = self.expression()?;
+        let value = self.step_expression()?;
         self.consume(&TokenType::EoStmt, "Expected ';' after value.")?;
-        return Ok(Statement::Expr(value));
+        Ok(Statement::Expr(value))
+    }
+
+    /// An expression standing on its own, which may also be `x++` or `x--`. Those are shorthand
+    /// for `x += 1` and `x -= 1` and have no value to use, so they are not allowed anywhere else.
+    fn step_expression(&mut self) -> Result<Expression, String> {
+        let expr = self.expression()?;
+        if !self.match_tokentype(&[TokenType::Increment, TokenType::Decrement]) {
+            return Ok(expr);
+        }
+        let step = self.previous().clone();
+        if !matches!(
+            expr,
+            Expression::Variable(_, _) | Expression::Index(_, _, _) | Expression::Get(_, _, _)
+        ) {
+            return self.error_ex(&step, STEP_ONLY);
+        }
+        let op = match step.ttype {
+            TokenType::Increment => TokenType::Plus,
+            _ => TokenType::Minus,
+        };
+        let one = Expression::Literal(Value::Int(1), step.index);
+        self.compound_assignment(expr, &step, op, one)
     }
 
     pub fn parse_expression(&mut self) -> Result<Expression, String> {
@@ -174,13 +873,60 @@ impl<'a> ExprStmtParser<'a> {
                 Expression::Variable(name, idx) => {
                     return Ok(Expression::Assignment(name, Box::new(value), idx));
                 }
+                Expression::Index(arr, idx, i_idx) => {
+                    return Ok(Expression::IndexAssignment(arr, idx, Box::new(value), i_idx));
+                }
+                Expression::Get(obj, name, g_idx) => {
+                    return Ok(Expression::SetField(obj, name, Box::new(value), g_idx));
+                }
                 _ => {}
             }
 
             return self.error_ex(&equals, "Invalid assignment target");
         }
 
-        return Ok(expr);
+        if self.match_tokentype(&[
+            TokenType::PlusAsign,
+            TokenType::MinusAsign,
+            TokenType::StarAsign,
+            TokenType::SlashAsign,
+            TokenType::ModAsign,
+        ]) {
+            let equals = self.previous().clone();
+            let value = self.assignment()?;
+            let op = match equals.ttype {
+                TokenType::PlusAsign => TokenType::Plus,
+                TokenType::MinusAsign => TokenType::Minus,
+                TokenType::StarAsign => TokenType::Star,
+                TokenType::SlashAsign => TokenType::Slash,
+                _ => TokenType::Mod,
+            };
+            return self.compound_assignment(expr, &equals, op, value);
+        }
+
+        Ok(expr)
+    }
+    fn compound_assignment(
+        &self,
+        target: Expression,
+        equals: &Token,
+        op: TokenType,
+        value: Expression,
+    ) -> Result<Expression, String> {
+        match target {
+            Expression::Variable(_, _) | Expression::Index(_, _, _) | Expression::Get(_, _, _) => {
+                let sign = Token {
+                    index: equals.index,
+                    ttype: op,
+                };
+                Ok(Expression::CompoundAssignment(
+                    Box::new(target),
+                    sign,
+                    Box::new(value),
+                ))
+            }
+            _ => self.error_ex(equals, "Invalid assignment target"),
+        }
     }
     fn boolean_logic(&mut self) -> Result<Expression, String> {
         let mut expr: Expression = self.equality()?;
@@ -191,7 +937,7 @@ impl<'a> ExprStmtParser<'a> {
             expr = Expression::Binary(Box::new(temp), op, Box::new(right));
         }
 
-        return Ok(expr);
+        Ok(expr)
     }
     fn equality(&mut self) -> Result<Expression, String> {
         let mut expr: Expression = self.comparison()?;
@@ -202,24 +948,72 @@ impl<'a> ExprStmtParser<'a> {
             expr = Expression::Binary(Box::new(temp), op, Box::new(right));
         }
 
-        return Ok(expr);
+        Ok(expr)
     }
 
     fn comparison(&mut self) -> Result<Expression, String> {
-        let mut expr: Expression = self.term()?;
+        let mut expr: Expression = self.bit_or()?;
         while self.match_tokentype(&[
             TokenType::Greater,
             TokenType::GreaterEquals,
             TokenType::Lesser,
             TokenType::LesserEquals,
         ]) {
+            let op: Token = self.previous().clone();
+            let right: Expression = self.bit_or()?;
+            let temp = expr;
+            expr = Expression::Binary(Box::new(temp), op, Box::new(right));
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_or(&mut self) -> Result<Expression, String> {
+        let mut expr: Expression = self.bit_xor()?;
+        while self.match_tokentype(&[TokenType::BitOr]) {
+            let op: Token = self.previous().clone();
+            let right: Expression = self.bit_xor()?;
+            let temp = expr;
+            expr = Expression::Binary(Box::new(temp), op, Box::new(right));
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_xor(&mut self) -> Result<Expression, String> {
+        let mut expr: Expression = self.bit_and()?;
+        while self.match_tokentype(&[TokenType::BitXor]) {
+            let op: Token = self.previous().clone();
+            let right: Expression = self.bit_and()?;
+            let temp = expr;
+            expr = Expression::Binary(Box::new(temp), op, Box::new(right));
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_and(&mut self) -> Result<Expression, String> {
+        let mut expr: Expression = self.shift()?;
+        while self.match_tokentype(&[TokenType::BitAnd]) {
+            let op: Token = self.previous().clone();
+            let right: Expression = self.shift()?;
+            let temp = expr;
+            expr = Expression::Binary(Box::new(temp), op, Box::new(right));
+        }
+
+        Ok(expr)
+    }
+
+    fn shift(&mut self) -> Result<Expression, String> {
+        let mut expr: Expression = self.term()?;
+        while self.match_tokentype(&[TokenType::ShiftLeft, TokenType::ShiftRight]) {
             let op: Token = self.previous().clone();
             let right: Expression = self.term()?;
             let temp = expr;
             expr = Expression::Binary(Box::new(temp), op, Box::new(right));
         }
 
-        return Ok(expr);
+        Ok(expr)
     }
 
     fn term(&mut self) -> Result<Expression, String> {
@@ -231,7 +1025,7 @@ impl<'a> ExprStmtParser<'a> {
             expr = Expression::Binary(Box::new(temp), op, Box::new(right));
         }
 
-        return Ok(expr);
+        Ok(expr)
     }
 
     fn factor(&mut self) -> Result<Expression, String> {
@@ -243,30 +1037,104 @@ impl<'a> ExprStmtParser<'a> {
             expr = Expression::Binary(Box::new(temp), op, Box::new(right));
         }
 
-        return Ok(expr);
+        Ok(expr)
     }
 
     fn unary(&mut self) -> Result<Expression, String> {
-        if self.match_tokentype(&[TokenType::Bang, TokenType::Minus]) {
+        if self.match_tokentype(&[TokenType::Bang, TokenType::Minus, TokenType::Tilde]) {
             let opr: Token = self.previous().clone();
             let right: Expression = self.unary()?;
             return Ok(Expression::Unary(opr, Box::new(right)));
         }
+        if self.match_tokentype(&[TokenType::Refference]) {
+            let idx = self.previous().index;
+            let mut name: String = "".to_string();
+            self.consume_identifier(&mut name, "Expected variable name after '@'")?;
+            return Ok(Expression::Reference(name, idx));
+        }
+        if self.match_tokentype(&[TokenType::Query]) {
+            let idx = self.previous().index;
+            let right: Expression = self.unary()?;
+            return Ok(Expression::Query(Box::new(right), idx));
+        }
+
+        self.power()
+    }
+
+    /// `**` binds tighter than a sign on its left and groups to the right, so `-2 ** 2` is `-4`.
+    fn power(&mut self) -> Result<Expression, String> {
+        let expr: Expression = self.call()?;
+        if self.match_tokentype(&[TokenType::Power]) {
+            let op: Token = self.previous().clone();
+            let right: Expression = self.unary()?;
+            return Ok(Expression::Binary(Box::new(expr), op, Box::new(right)));
+        }
+
+        Ok(expr)
+    }
 
-        return self.primary();
+    fn call(&mut self) -> Result<Expression, String> {
+        let mut expr: Expression = self.primary()?;
+        loop {
+            if self.match_tokentype(&[TokenType::OpenSquare]) {
+                let idx = self.previous().index;
+                let position: Expression = self.expression()?;
+                self.consume(&TokenType::CloseSquare, "Expected ']' after index")?;
+                expr = Expression::Index(Box::new(expr), Box::new(position), idx);
+                continue;
+            }
+            if self.match_tokentype(&[TokenType::Period]) {
+                let idx = self.previous().index;
+                let mut name: String = "".to_string();
+                self.consume_identifier(&mut name, "Expected member name after '.'")?;
+                expr = Expression::Get(Box::new(expr), name, idx);
+                continue;
+            }
+            if !self.match_tokentype(&[TokenType::OpenParent]) {
+                break;
+            }
+            let idx = self.previous().index;
+            let mut args: Vec<Expression> = Vec::<Expression>::new();
+            if !self.check(&TokenType::CloseParent) {
+                loop {
+                    args.push(self.expression()?);
+                    if !self.match_tokentype(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.consume(&TokenType::CloseParent, "Expected ')' after arguments")?;
+            expr = Expression::Call(Box::new(expr), args, idx);
+        }
+
+        Ok(expr)
     }
 
     fn primary(&mut self) -> Result<Expression, String> {
         let mut index = 0;
+        if self.match_tokentype(&[TokenType::Int, TokenType::Num]) {
+            let target = self.previous().clone();
+            self.consume(&TokenType::OpenParent, "Expected '(' after type name")?;
+            let value = self.expression()?;
+            self.consume(&TokenType::CloseParent, "Expected ')' after value")?;
+            let vtype = VarType::from_token(&target.ttype).unwrap_or(VarType::Num);
+            return Ok(Expression::Convert(vtype, Box::new(value), target.index));
+        }
+        if self.match_tokentype(&[TokenType::Fxn]) {
+            return self.anonymous_fxn();
+        }
         if self.match_tokentype_index(&[TokenType::False], &mut index) {
             return Ok(Expression::Literal(Value::Boolean(false), index));
         }
         if self.match_tokentype_index(&[TokenType::True], &mut index) {
             return Ok(Expression::Literal(Value::Boolean(true), index));
         }
+        if self.match_tokentype_index(&[TokenType::Null], &mut index) {
+            return Ok(Expression::Literal(Value::Null, index));
+        }
 
         if let Some((num, id)) = self.match_number_literal() {
-            return Ok(Expression::Literal(Value::Number(num), id));
+            return Ok(Expression::Literal(num, id));
         }
         if let Some((b, id)) = self.match_boolean_literal() {
             return Ok(Expression::Literal(Value::Boolean(b), id));
@@ -275,6 +1143,9 @@ impl<'a> ExprStmtParser<'a> {
             return Ok(Expression::Literal(Value::StringVal(string_value), id));
         }
         if let Some((identifer, id)) = self.match_identifier() {
+            if self.struct_literal_ahead() {
+                return self.struct_literal(identifer, id);
+            }
             return Ok(Expression::Variable(identifer, id));
         }
 
@@ -288,13 +1159,70 @@ impl<'a> ExprStmtParser<'a> {
             return Ok(Expression::Group(Box::new(expr)));
         }
 
-        // if self.match_tokentype(&[TokenType::OpenSquare]) {
-        //     let expr: Expression = self.expression()?;
-        //     self.consume(&TokenType::CloseSquare, "Expected ')' after expression")?;
-        //     return Ok(Expression::Index(Box::new(expr)));
-        // }
+        if self.match_tokentype(&[TokenType::OpenSquare]) {
+            let idx = self.previous().index;
+            let mut items: Vec<Expression> = Vec::<Expression>::new();
+            if !self.check(&TokenType::CloseSquare) {
+                loop {
+                    items.push(self.expression()?);
+                    if !self.match_tokentype(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.consume(&TokenType::CloseSquare, "Expected ']' after array items")?;
+            return Ok(Expression::ArrayLiteral(items, idx));
+        }
+
+        if self.match_tokentype(&[TokenType::OpenBrace]) {
+            let idx = self.previous().index;
+            let mut entries: Vec<(Expression, Expression)> = Vec::new();
+            if !self.check(&TokenType::CloseBrace) {
+                loop {
+                    let key = self.expression()?;
+                    self.consume(&TokenType::Colon, "Expected ':' after map key")?;
+                    let val = self.expression()?;
+                    entries.push((key, val));
+                    if !self.match_tokentype(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.consume(&TokenType::CloseBrace, "Expected '}' after map entries")?;
+            return Ok(Expression::MapLiteral(entries, idx));
+        }
+
+        self.error_ex(self.peek(), "Expected expression")
+    }
 
-        return self.error_ex(self.peek(), "Expected expression");
+    /// `Name {` starts a struct literal only when followed by `}` or `field:`, so blocks after a name still parse.
+    fn struct_literal_ahead(&self) -> bool {
+        if !self.check(&TokenType::OpenBrace) {
+            return false;
+        }
+        match self.tokens.get(self.current + 1).map(|t| &t.ttype) {
+            Some(TokenType::CloseBrace) => true,
+            Some(TokenType::Identifier(_)) => matches!(
+                self.tokens.get(self.current + 2).map(|t| &t.ttype),
+                Some(TokenType::Colon)
+            ),
+            _ => false,
+        }
+    }
+    fn struct_literal(&mut self, name: String, idx: usize) -> Result<Expression, String> {
+        self.consume(&TokenType::OpenBrace, "Expected '{' after struct name")?;
+        let mut fields: Vec<(String, Expression)> = Vec::new();
+        while !self.check(&TokenType::CloseBrace) && !self.is_at_end() {
+            let mut field: String = "".to_string();
+            self.consume_identifier(&mut field, "Expected field name")?;
+            self.consume(&TokenType::Colon, "Expected ':' after field name")?;
+            fields.push((field, self.expression()?));
+            if !self.match_tokentype(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(&TokenType::CloseBrace, "Expected '}' after struct fields")?;
+        Ok(Expression::StructLiteral(name, fields, idx))
     }
 
     //helper functions
@@ -310,9 +1238,9 @@ impl<'a> ExprStmtParser<'a> {
     }
     fn advance(&mut self) -> &Token {
         if !self.is_at_end() {
-            self.current = self.current + 1;
+            self.current += 1;
         }
-        return self.previous();
+        self.previous()
     }
     fn error(&self, token: &Token, msg: &str) -> Result<&Token, String> {
         Result::Err(format!(
@@ -328,6 +1256,13 @@ impl<'a> ExprStmtParser<'a> {
             self.get_line_from_index(token.index)
         ))
     }
+    fn error_type(&self, token: &Token, msg: &str) -> Result<VarType, String> {
+        Result::Err(format!(
+            "{} at line {}",
+            msg,
+            self.get_line_from_index(token.index)
+        ))
+    }
     fn synchronize(&mut self) {
         self.advance();
         while !self.is_at_end() {
@@ -337,13 +1272,25 @@ impl<'a> ExprStmtParser<'a> {
 
             match self.peek().ttype {
                 TokenType::Fxn => return,
+                TokenType::Struct => return,
+                TokenType::Enum => return,
                 TokenType::If => return,
                 TokenType::While => return,
+                TokenType::For => return,
+                TokenType::Match => return,
+                TokenType::Break => return,
+                TokenType::Continue => return,
+                TokenType::Throw => return,
+                TokenType::Try => return,
                 TokenType::Var => return,
+                TokenType::Const => return,
                 TokenType::Num => return,
+                TokenType::Int => return,
                 TokenType::Str => return,
                 TokenType::Bool => return,
                 TokenType::Return => return,
+                TokenType::Import => return,
+                TokenType::Export => return,
                 _ => {}
             }
 
@@ -354,14 +1301,17 @@ impl<'a> ExprStmtParser<'a> {
         if self.check(ttype) {
             return Ok(self.advance());
         }
-        return self.error(self.peek(), err_msg);
+        if self.check(&TokenType::Increment) || self.check(&TokenType::Decrement) {
+            return self.error(self.peek(), STEP_ONLY);
+        }
+        self.error(self.peek(), err_msg)
     }
 
     fn consume_identifier(&mut self, id: &mut String, err_msg: &str) -> Result<&Token, String> {
         if self.check_identifier(id) {
             return Ok(self.advance());
         }
-        return self.error(self.peek(), err_msg);
+        self.error(self.peek(), err_msg)
     }
 
     fn check_identifier(&self, id: &mut String) -> bool {
@@ -373,13 +1323,19 @@ impl<'a> ExprStmtParser<'a> {
             return true;
         }
 
-        return false;
+        false
+    }
+    fn check_next(&self, ttype: &TokenType) -> bool {
+        if self.current + 1 >= self.tokens.len() {
+            return false;
+        }
+        self.tokens[self.current + 1].ttype == *ttype
     }
     fn check(&self, ttype: &TokenType) -> bool {
         if self.is_at_end() {
             return false;
         }
-        return self.peek().ttype == *ttype;
+        self.peek().ttype == *ttype
     }
     fn match_tokentype(&mut self, ttypes: &[TokenType]) -> bool {
         for tt in ttypes {
@@ -388,7 +1344,7 @@ impl<'a> ExprStmtParser<'a> {
                 return true;
             }
         }
-        return false;
+        false
     }
     fn match_tokentype_index(&mut self, ttypes: &[TokenType], index: &mut usize) -> bool {
         *index = self.peek().index;
@@ -398,16 +1354,17 @@ impl<'a> ExprStmtParser<'a> {
                 return true;
             }
         }
-        return false;
+        false
     }
-    fn match_number_literal(&mut self) -> Option<(f64, usize)> {
+    fn match_number_literal(&mut self) -> Option<(Value, usize)> {
         let index = self.peek().index;
-        if let TokenType::NumberLiteral(num) = self.peek().ttype {
-            self.advance(); // Move past the number
-            Some((num, index)) // Return the extracted number
-        } else {
-            None
-        }
+        let value = match self.peek().ttype {
+            TokenType::NumberLiteral(num) => Value::Number(num),
+            TokenType::IntegerLiteral(num) => Value::Int(num),
+            _ => return None,
+        };
+        self.advance(); // Move past the number
+        Some((value, index)) // Return the extracted number
     }
     fn match_boolean_literal(&mut self) -> Option<(bool, usize)> {
         let index = self.peek().index;
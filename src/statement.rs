@@ -1,8 +1,11 @@
 use crate::{
-    badger_debug::error,
+    badger_debug::{caught, error, throw, RuntimeError},
+    enums::EnumDecl,
     expression::{boolify, Expression, Value},
+    function::{Function, FunctionDecl},
+    structs::StructDecl,
     symbol_table::SymbolTable,
-    tokenizer::TokenType,
+    var_type::VarType,
 };
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -14,15 +17,114 @@ pub enum Statement {
     Block(Vec<Statement>),
     IfStmt(Expression, Box<Statement>, Box<Option<Statement>>),
     WhileStmt(Expression, Box<Statement>),
-    VarDecl(String, Expression, TokenType, usize),
+    ForStmt(
+        Box<Option<Statement>>,
+        Option<Expression>,
+        Option<Expression>,
+        Box<Statement>,
+    ),
+    ForEach(String, Expression, Box<Statement>, usize),
+    Match(Expression, Vec<MatchArm>, usize),
+    VarDecl(String, Expression, VarType, usize),
+    ConstDecl(String, Expression, VarType, usize),
+    FxnDecl(Rc<FunctionDecl>),
+    StructDecl(Rc<StructDecl>),
+    EnumDecl(Rc<EnumDecl>),
+    Break(usize),
+    Continue(usize),
+    Throw(Expression, usize),
+    // body, the `catch` variable and handler, and the `finally` block
+    Try(Box<Statement>, Option<(String, Box<Statement>)>, Box<Option<Statement>>, usize),
+    Import(String, usize),
+    Export(Box<Statement>),
+    // a declaration with the text of the `##` comment above it
+    Doc(String, Box<Statement>),
+}
+
+#[derive(Clone)]
+pub enum Pattern {
+    Literal(Value),
+    // numbers from the first bound up to, but not including, the second
+    Range(f64, f64),
+    // `Enum.Variant(a, b)`: the enum, the variant and the names its values are bound to
+    Variant(String, String, Vec<String>, usize),
+    Wildcard,
+}
+
+#[derive(Clone)]
+pub struct MatchArm {
+    pub patterns: Vec<Pattern>,
+    pub body: Statement,
+}
+
+impl Pattern {
+    pub fn matches(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Pattern::Wildcard, _) => true,
+            (Pattern::Range(from, to), Value::Number(_) | Value::Int(_)) => {
+                *from <= value.as_number() && value.as_number() < *to
+            }
+            (Pattern::Literal(Value::Int(a)), Value::Int(b)) => a == b,
+            (Pattern::Literal(lit @ (Value::Number(_) | Value::Int(_))), Value::Number(_) | Value::Int(_)) => {
+                lit.as_number() == value.as_number()
+            }
+            (Pattern::Literal(Value::StringVal(a)), Value::StringVal(b)) => a == b,
+            (Pattern::Literal(Value::Boolean(a)), Value::Boolean(b)) => a == b,
+            (Pattern::Literal(Value::Null), Value::Null) => true,
+            (Pattern::Variant(enum_name, variant, _, _), Value::Variant(v)) => {
+                v.decl.name == *enum_name && v.name() == variant
+            }
+            _ => false,
+        }
+    }
+    /// The scope an arm runs in, holding the values a variant pattern binds.
+    fn bind(
+        &self,
+        value: &Value,
+        table: Rc<RefCell<SymbolTable>>,
+        lines: &[usize],
+    ) -> Result<Rc<RefCell<SymbolTable>>, RuntimeError> {
+        let (names, index, payload) = match (self, value) {
+            (Pattern::Variant(_, _, names, index), Value::Variant(v)) if !names.is_empty() => {
+                (names, index, &v.payload)
+            }
+            _ => return Ok(table),
+        };
+        if names.len() != payload.len() {
+            return error(
+                &format!(
+                    "Pattern binds {} values but the variant has {}",
+                    names.len(),
+                    payload.len()
+                ),
+                index,
+                lines,
+            );
+        }
+        let mut arm_table = SymbolTable::new(Some(table));
+        for (name, val) in names.iter().zip(payload.iter()) {
+            if name != "_" {
+                arm_table.add_symbol(name, val.clone(), VarType::Var, index, lines)?;
+            }
+        }
+        Ok(Rc::new(RefCell::new(arm_table)))
+    }
+}
+
+/// How control leaves a statement once it has been executed.
+pub enum Flow {
+    Normal,
+    Return(Value),
+    Break,
+    Continue,
 }
 
 impl Statement {
     pub fn accept(
         &self,
         table: Rc<RefCell<SymbolTable>>,
-        debug_lines: &Vec<usize>,
-    ) -> Result<Value, String> {
+        debug_lines: &[usize],
+    ) -> Result<Flow, RuntimeError> {
         match self {
             Self::Return(rexpr) => {
                 Self::visit_return(rexpr, Rc::clone(&table), debug_lines)
@@ -31,7 +133,10 @@ impl Statement {
                 Self::visit_expr(expr, Rc::clone(&table), debug_lines)
             }
             Self::VarDecl(name, init, vtype, index) => {
-                Self::visit_var_decl(name, vtype, init, Rc::clone(&table), index, debug_lines)
+                Self::visit_var_decl(name, vtype, init, false, Rc::clone(&table), index, debug_lines)
+            }
+            Self::ConstDecl(name, init, vtype, index) => {
+                Self::visit_var_decl(name, vtype, init, true, Rc::clone(&table), index, debug_lines)
             }
             Self::Block(statements) => {
                 let new_table = SymbolTable::new(Some(Rc::clone(&table)));
@@ -43,6 +148,82 @@ impl Statement {
             Self::WhileStmt(condition, body) => {
                 Self::execute_while(condition, body, Rc::clone(&table), debug_lines)
             }
+            Self::ForStmt(init, condition, step, body) => Self::execute_for(
+                init,
+                condition,
+                step,
+                body,
+                Rc::clone(&table),
+                debug_lines,
+            ),
+            Self::ForEach(name, collection, body, index) => Self::execute_foreach(
+                name,
+                collection,
+                body,
+                Rc::clone(&table),
+                index,
+                debug_lines,
+            ),
+            Self::Match(subject, arms, _index) => {
+                let value = subject.evaluate(Rc::clone(&table), debug_lines)?;
+                for arm in arms {
+                    if let Some(pattern) = arm.patterns.iter().find(|p| p.matches(&value)) {
+                        let arm_table = pattern.bind(&value, Rc::clone(&table), debug_lines)?;
+                        return arm.body.accept(arm_table, debug_lines);
+                    }
+                }
+                Ok(Flow::Normal)
+            }
+            Self::FxnDecl(decl) => Self::visit_fxn_decl(decl, Rc::clone(&table), debug_lines),
+            Self::StructDecl(decl) => {
+                table.borrow_mut().add_symbol(
+                    &decl.name,
+                    Value::Struct(Rc::clone(decl)),
+                    VarType::Var,
+                    &decl.index,
+                    debug_lines,
+                )?;
+                Ok(Flow::Normal)
+            }
+            Self::Throw(expr, index) => {
+                let value = expr.evaluate(Rc::clone(&table), debug_lines)?;
+                throw(value, index, debug_lines)
+            }
+            Self::Try(body, handler, finally, index) => {
+                Self::execute_try(body, handler, finally, Rc::clone(&table), index, debug_lines)
+            }
+            Self::EnumDecl(decl) => {
+                table.borrow_mut().add_symbol(
+                    &decl.name,
+                    Value::Enum(Rc::clone(decl)),
+                    VarType::Var,
+                    &decl.index,
+                    debug_lines,
+                )?;
+                Ok(Flow::Normal)
+            }
+            Self::Break(_) => Ok(Flow::Break),
+            Self::Continue(_) => Ok(Flow::Continue),
+            Self::Import(_, index) => error(
+                "'import' is only allowed at the top level",
+                index,
+                debug_lines,
+            ),
+            Self::Export(inner) => inner.accept(table, debug_lines),
+            Self::Doc(_, inner) => inner.accept(table, debug_lines),
+        }
+    }
+    /// The name bound by a declaration, if this statement is one.
+    pub fn declared_name(&self) -> Option<String> {
+        match self {
+            Self::VarDecl(name, _, _, _) => Some(name.clone()),
+            Self::ConstDecl(name, _, _, _) => Some(name.clone()),
+            Self::FxnDecl(decl) => Some(decl.name.clone()),
+            Self::StructDecl(decl) => Some(decl.name.clone()),
+            Self::EnumDecl(decl) => Some(decl.name.clone()),
+            Self::Export(inner) => inner.declared_name(),
+            Self::Doc(_, inner) => inner.declared_name(),
+            _ => None,
         }
     }
     fn execute_if(
@@ -50,123 +231,222 @@ impl Statement {
         then: &Statement,
         else_branch: &Option<Statement>,
         table: Rc<RefCell<SymbolTable>>,
-        lines: &Vec<usize>,
-    ) -> Result<Value, String> {
+        lines: &[usize],
+    ) -> Result<Flow, RuntimeError> {
         let value = condition.evaluate(Rc::clone(&table), lines)?;
         let truthy = boolify(&value);
         if truthy {
-            then.accept(table, lines)?;
-            return Ok(Value::Boolean(true));
+            return then.accept(table, lines);
         } else {
-            match else_branch {
-                Some(stmt) => {
-                    stmt.accept(table, lines)?;
-                }
-                _ => {}
+            if let Some(stmt) = else_branch {
+                return stmt.accept(table, lines);
             }
         }
 
-        return Ok(Value::Boolean(false));
+        Ok(Flow::Normal)
+    }
+
+    /// Runtime errors and thrown values go to the handler; `finally` runs however the body ends.
+    fn execute_try(
+        body: &Statement,
+        handler: &Option<(String, Box<Statement>)>,
+        finally: &Option<Statement>,
+        table: Rc<RefCell<SymbolTable>>,
+        index: &usize,
+        lines: &[usize],
+    ) -> Result<Flow, RuntimeError> {
+        let result = match (body.accept(Rc::clone(&table), lines), handler) {
+            (Err(err), Some((name, catch_body))) => {
+                let mut catch_table = SymbolTable::new(Some(Rc::clone(&table)));
+                catch_table.add_symbol(name, caught(err), VarType::Var, index, lines)?;
+                catch_body.accept(Rc::new(RefCell::new(catch_table)), lines)
+            }
+            (result, _) => result,
+        };
+
+        if let Some(stmt) = finally {
+            // leaving `finally` early replaces how the body ended
+            match stmt.accept(table, lines)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+
+        result
     }
 
     fn execute_while(
         condition: &Expression,
         then: &Statement,
         table: Rc<RefCell<SymbolTable>>,
-        lines: &Vec<usize>,
-    ) -> Result<Value, String> {
+        lines: &[usize],
+    ) -> Result<Flow, RuntimeError> {
         let mut value = condition.evaluate(Rc::clone(&table), lines)?;
         let mut truthy = boolify(&value);
 
         while truthy {
-            then.accept(Rc::clone(&table), lines)?;
+            match then.accept(Rc::clone(&table), lines)? {
+                Flow::Normal | Flow::Continue => {}
+                Flow::Break => break,
+                flow => return Ok(flow),
+            }
             value = condition.evaluate(Rc::clone(&table), lines)?;
             truthy = boolify(&value);
         }
 
-        Ok(Value::Boolean(false))
+        Ok(Flow::Normal)
+    }
+
+    fn execute_for(
+        init: &Option<Statement>,
+        condition: &Option<Expression>,
+        step: &Option<Expression>,
+        body: &Statement,
+        table: Rc<RefCell<SymbolTable>>,
+        lines: &[usize],
+    ) -> Result<Flow, RuntimeError> {
+        // the loop variable lives in its own scope around the body
+        let loop_table = Rc::new(RefCell::new(SymbolTable::new(Some(table))));
+        if let Some(init_stmt) = init {
+            init_stmt.accept(Rc::clone(&loop_table), lines)?;
+        }
+
+        loop {
+            if let Some(cond) = condition {
+                let value = cond.evaluate(Rc::clone(&loop_table), lines)?;
+                if !boolify(&value) {
+                    break;
+                }
+            }
+            match body.accept(Rc::clone(&loop_table), lines)? {
+                Flow::Normal | Flow::Continue => {}
+                Flow::Break => break,
+                flow => return Ok(flow),
+            }
+            if let Some(step_expr) = step {
+                step_expr.evaluate(Rc::clone(&loop_table), lines)?;
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn execute_foreach(
+        name: &str,
+        collection: &Expression,
+        body: &Statement,
+        table: Rc<RefCell<SymbolTable>>,
+        index: &usize,
+        lines: &[usize],
+    ) -> Result<Flow, RuntimeError> {
+        let items: Vec<Value> = match collection.evaluate(Rc::clone(&table), lines)? {
+            Value::Array(arr) => arr.borrow().clone(),
+            Value::Map(entries) => entries
+                .borrow()
+                .iter()
+                .map(|(k, _)| Value::StringVal(k.clone()))
+                .collect(),
+            Value::StringVal(s) => s.chars().map(|c| Value::StringVal(c.to_string())).collect(),
+            _ => return error("Can only loop over arrays, maps and strings", index, lines),
+        };
+
+        for item in items {
+            let mut iter_table = SymbolTable::new(Some(Rc::clone(&table)));
+            iter_table.add_symbol(name, item, VarType::Var, index, lines)?;
+            match body.accept(Rc::new(RefCell::new(iter_table)), lines)? {
+                Flow::Normal | Flow::Continue => {}
+                Flow::Break => break,
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Normal)
     }
 
     fn execute_block(
         statements: &Vec<Statement>,
         table: SymbolTable,
-        debug_lines: &Vec<usize>,
-    ) -> Result<Value, String> {
+        debug_lines: &[usize],
+    ) -> Result<Flow, RuntimeError> {
         let local_table = Rc::<RefCell<SymbolTable>>::new(RefCell::new(table));
         for stmt in statements {
-            stmt.accept(Rc::clone(&local_table), debug_lines)?;
+            match stmt.accept(Rc::clone(&local_table), debug_lines)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
         }
 
-        return Ok(Value::Boolean(true));
+        Ok(Flow::Normal)
     }
     fn visit_var_decl(
-        name: &String,
-        vtype: &TokenType,
+        name: &str,
+        vtype: &VarType,
         init: &Expression,
+        constant: bool,
         table: Rc<RefCell<SymbolTable>>,
         index: &usize,
-        debug_lines: &Vec<usize>,
-    ) -> Result<Value, String> {
+        debug_lines: &[usize],
+    ) -> Result<Flow, RuntimeError> {
         let value = init.evaluate(Rc::clone(&table), debug_lines)?;
 
-        match value {
-            Value::Number(_) => match vtype {
-                TokenType::Num => {}
-                TokenType::Var => {}
-                _ => {
-                    return error(
-                        "Expression is not of expected type (num)",
-                        index,
-                        debug_lines,
-                    );
-                }
-            },
-            Value::Boolean(_) => match vtype {
-                TokenType::Bool => {}
-                TokenType::Var => {}
-                _ => {
-                    return error(
-                        "Expression is not of expected type (bool)",
-                        index,
-                        debug_lines,
-                    );
-                }
-            },
-            Value::StringVal(_) => match vtype {
-                TokenType::Str => {}
-                TokenType::Var => {}
-                _ => {
-                    return error(
-                        "Expression is not of expected type (str)",
-                        index,
-                        debug_lines,
-                    );
-                }
-            },
+        if !value.is_of_type(vtype) {
+            return error(
+                &format!("Expression is not of expected type ({})", vtype),
+                index,
+                debug_lines,
+            );
         }
 
-        table.borrow_mut().add_symbol(name, value, index, debug_lines)?;
+        // a `var` keeps the type of the value it was first given
+        let slot_type = match vtype {
+            VarType::Var => value.infer_type(),
+            _ => vtype.clone(),
+        };
+        if constant {
+            table
+                .borrow_mut()
+                .add_constant(name, value, slot_type, index, debug_lines)?;
+        } else {
+            table
+                .borrow_mut()
+                .add_symbol(name, value, slot_type, index, debug_lines)?;
+            if *vtype == VarType::Var {
+                table.borrow_mut().inferred.insert(name.to_owned());
+            }
+        }
 
-        return Ok(Value::Boolean(true));
+        Ok(Flow::Normal)
+    }
+    fn visit_fxn_decl(
+        decl: &Rc<FunctionDecl>,
+        table: Rc<RefCell<SymbolTable>>,
+        debug_lines: &[usize],
+    ) -> Result<Flow, RuntimeError> {
+        let fx = Function::new(Rc::clone(decl), Rc::clone(&table));
+        table.borrow_mut().add_symbol(
+            &decl.name,
+            Value::Function(Rc::new(fx)),
+            VarType::Fxn,
+            &decl.index,
+            debug_lines,
+        )?;
+
+        Ok(Flow::Normal)
     }
     fn visit_expr(
         expr: &Expression,
         table: Rc<RefCell<SymbolTable>>,
-        debug_lines: &Vec<usize>,
-    ) -> Result<Value, String> {
-        return expr.evaluate(table, debug_lines);
+        debug_lines: &[usize],
+    ) -> Result<Flow, RuntimeError> {
+        expr.evaluate(table, debug_lines)?;
+        Ok(Flow::Normal)
     }
     fn visit_return(
         expr: &Expression,
         table: Rc<RefCell<SymbolTable>>,
-        debug_lines: &Vec<usize>,
-    ) -> Result<Value, String> {
+        debug_lines: &[usize],
+    ) -> Result<Flow, RuntimeError> {
         let val: Value = expr.evaluate(table, debug_lines)?;
-        match &val {
-            Value::Boolean(b) => println!("{}", b),
-            Value::StringVal(s) => println!("{}", s),
-            Value::Number(n) => println!("{}", n),
-        }
-        return Ok(val);
+        Ok(Flow::Return(val))
     }
 }
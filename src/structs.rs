@@ -0,0 +1,127 @@
+use crate::{
+    badger_debug::{error, RuntimeError},
+    expression::Value,
+    var_type::VarType,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub struct Field {
+    pub name: String,
+    pub ftype: VarType,
+    pub index: usize,
+}
+
+pub struct StructDecl {
+    pub name: String,
+    pub fields: Vec<Field>,
+    pub index: usize,
+}
+
+pub struct Instance {
+    pub decl: Rc<StructDecl>,
+    // one value per declared field, in declaration order
+    pub values: RefCell<Vec<Value>>,
+}
+
+impl StructDecl {
+    /// Builds an instance from `name: value` pairs. Nullable fields that are left out start as `null`.
+    pub fn construct(
+        decl: &Rc<StructDecl>,
+        given: Vec<(String, Value)>,
+        index: &usize,
+        lines: &[usize],
+    ) -> Result<Value, RuntimeError> {
+        let mut values: Vec<Option<Value>> = vec![None; decl.fields.len()];
+        for (name, val) in given {
+            let at = decl.field_position(&name, index, lines)?;
+            if values[at].is_some() {
+                return error(
+                    &format!("Field '{}' is given more than once", name),
+                    index,
+                    lines,
+                );
+            }
+            decl.check_field(at, &val, index, lines)?;
+            values[at] = Some(val.coerce(&decl.fields[at].ftype));
+        }
+
+        let mut filled: Vec<Value> = Vec::<Value>::new();
+        for (field, val) in decl.fields.iter().zip(values) {
+            match val {
+                Some(v) => filled.push(v),
+                None if matches!(field.ftype, VarType::Nullable(_)) => filled.push(Value::Null),
+                None => {
+                    return error(
+                        &format!("Missing field '{}' for '{}'", field.name, decl.name),
+                        index,
+                        lines,
+                    )
+                }
+            }
+        }
+
+        Ok(Value::Instance(Rc::new(Instance {
+            decl: Rc::clone(decl),
+            values: RefCell::new(filled),
+        })))
+    }
+
+    fn field_position(
+        &self,
+        name: &str,
+        index: &usize,
+        lines: &[usize],
+    ) -> Result<usize, RuntimeError> {
+        match self.fields.iter().position(|f| f.name == name) {
+            Some(at) => Ok(at),
+            None => error(
+                &format!("'{}' has no field named '{}'", self.name, name),
+                index,
+                lines,
+            ),
+        }
+    }
+
+    fn check_field(
+        &self,
+        at: usize,
+        val: &Value,
+        index: &usize,
+        lines: &[usize],
+    ) -> Result<(), RuntimeError> {
+        let field = &self.fields[at];
+        if !val.is_of_type(&field.ftype) {
+            return error(
+                &format!(
+                    "Field '{}' of '{}' is not of expected type ({})",
+                    field.name, self.name, field.ftype
+                ),
+                index,
+                lines,
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Instance {
+    pub fn get(&self, name: &str, index: &usize, lines: &[usize]) -> Result<Value, RuntimeError> {
+        let at = self.decl.field_position(name, index, lines)?;
+        Ok(self.values.borrow()[at].clone())
+    }
+
+    pub fn set(
+        &self,
+        name: &str,
+        val: Value,
+        index: &usize,
+        lines: &[usize],
+    ) -> Result<Value, RuntimeError> {
+        let at = self.decl.field_position(name, index, lines)?;
+        self.decl.check_field(at, &val, index, lines)?;
+        let val = val.coerce(&self.decl.fields[at].ftype);
+        self.values.borrow_mut()[at] = val.clone();
+        Ok(val)
+    }
+}
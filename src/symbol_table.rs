@@ -1,15 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::cell::RefCell;
 
 use crate::{
-    badger_debug::{error, get_col, get_line_from_index},
+    badger_debug::{error, RuntimeError},
     expression::Value,
+    var_type::VarType,
 };
 
 pub struct SymbolTable {
     pub global_counter: u64,
     pub map: HashMap<String, Value>,
+    pub types: HashMap<String, VarType>,
+    // names declared `const`, which cannot be assigned to again
+    pub constants: HashSet<String>,
+    // `var`s typed by their first value, whose `int` slots widen to `num` when given one
+    pub inferred: HashSet<String>,
     pub encolsing: Option<Rc<RefCell<SymbolTable>>>,
 }
 
@@ -18,27 +24,55 @@ impl SymbolTable {
         &mut self,
         name: &str,
         value: Value,
+        vtype: VarType,
         index: &usize,
-        lines: &Vec<usize>,
-    ) -> Result<u64, String> {
+        lines: &[usize],
+    ) -> Result<u64, RuntimeError> {
         if self.map.contains_key(name) {
-            let l = get_line_from_index(lines, index);
-            let c = get_col(index, lines);
-            return Result::Err(format!(
-                "{} at line {}, {}",
-                "Identifier already decleared", l, c
-            ));
+            return error("Identifier already decleared", index, lines);
         }
 
-        self.map.insert(name.to_owned(), value);
-        self.global_counter = self.global_counter + 1;
+        self.map.insert(name.to_owned(), value.coerce(&vtype));
+        self.types.insert(name.to_owned(), vtype);
+        self.global_counter += 1;
 
-        return Ok(self.global_counter - 1);
+        Ok(self.global_counter - 1)
+    }
+    pub fn add_constant(
+        &mut self,
+        name: &str,
+        value: Value,
+        vtype: VarType,
+        index: &usize,
+        lines: &[usize],
+    ) -> Result<u64, RuntimeError> {
+        let id = self.add_symbol(name, value, vtype, index, lines)?;
+        self.constants.insert(name.to_owned());
+        Ok(id)
+    }
+    /// Finds the table in the scope chain that owns `name`.
+    pub fn resolve(
+        table: &Rc<RefCell<SymbolTable>>,
+        name: &str,
+        level: usize,
+    ) -> Option<Rc<RefCell<SymbolTable>>> {
+        if table.borrow().map.contains_key(name) {
+            return Some(Rc::clone(table));
+        }
+        if level < 256 {
+            if let Some(parent) = &table.borrow().encolsing {
+                return SymbolTable::resolve(parent, name, level + 1);
+            }
+        }
+        None
     }
     pub fn new(parent_scope: Option<Rc<RefCell<SymbolTable>>>) -> SymbolTable {
         SymbolTable {
             global_counter: 1000,
             map: HashMap::new(),
+            types: HashMap::new(),
+            constants: HashSet::new(),
+            inferred: HashSet::new(),
             encolsing: parent_scope,
         }
     }
@@ -47,101 +81,98 @@ impl SymbolTable {
         name: &str,
         val: Value,
         index: &usize,
-        lines: &Vec<usize>,
+        lines: &[usize],
         level: usize
-    ) -> Result<Value, String> {
-        let og_value = self.get_from_symbol(name, index, lines, 0)?;
-
-        match og_value {
-            Value::Boolean(_b) => match val {
-                Value::Boolean(_) => {}
-                _ => {
-                    return error(
-                        "Cannot assign different typed value to different types variable",
-                        index,
-                        lines,
-                    )
-                }
-            },
-            Value::Number(_b) => match val {
-                Value::Number(_) => {}
-                _ => {
-                    return error(
-                        "Cannot assign different typed value to different types variable",
-                        index,
-                        lines,
-                    )
-                }
-            },
-            Value::StringVal(_b) => match val {
-                Value::StringVal(_) => {}
-                _ => {
+    ) -> Result<Value, RuntimeError> {
+        if self.map.contains_key(name) {
+            if self.constants.contains(name) {
+                return error(
+                    &format!("Cannot assign to constant '{}'", name),
+                    index,
+                    lines,
+                );
+            }
+            let mut vtype = self.types.get(name).cloned().unwrap_or(VarType::Var);
+            if !val.is_of_type(&vtype)
+                && self.inferred.contains(name)
+                && val.is_of_type(&vtype.widened())
+            {
+                vtype = vtype.widened();
+                self.types.insert(name.to_owned(), vtype.clone());
+            }
+            if !val.is_of_type(&vtype) {
+                if let Value::Null = val {
                     return error(
-                        "Cannot assign different typed value to different types variable",
+                        &format!("Cannot assign null to '{}' of non-nullable type ({})", name, vtype),
                         index,
                         lines,
-                    )
+                    );
                 }
-            },
-        }
-        if self.map.contains_key(name) {
+                return error(
+                    "Cannot assign different typed value to different types variable",
+                    index,
+                    lines,
+                );
+            }
+            let val = val.coerce(&vtype);
             *self.map.get_mut(name).unwrap() = val.clone();
-        } else {
-            match &mut self.encolsing {
-                Some(tbl) => {
-                    if level < 256 {
-                        tbl.borrow_mut().set_var_val(name, val.clone(), index, lines,level + 1)?;
-                    }
-                }
-                _ => {}
+            return Ok(val);
+        }
+
+        if let Some(tbl) = &mut self.encolsing {
+            if level < 256 {
+                return tbl
+                    .borrow_mut()
+                    .set_var_val(name, val, index, lines, level + 1);
             }
         }
-        return Ok(val);
+        error(&format!("Symbol '{}' does not exist!", name), index, lines)
     }
 
     pub fn get_from_symbol(
         &self,
         var_name: &str,
         index: &usize,
-        lines: &Vec<usize>,
+        lines: &[usize],
         level: usize,
-    ) -> Result<Value, String> {
+    ) -> Result<Value, RuntimeError> {
         if self.map.contains_key(var_name) {
             let entry = self.map.get(var_name);
 
             match entry {
-                Some(val) => return Ok(val.clone()),
+                Some(val) => Ok(val.clone()),
                 _ => {
                     if level < 256 {
-                        match &self.encolsing {
-                            Some(ref table) => {
-                                return table.borrow_mut().get_from_symbol(var_name, index, lines, level + 1);
-                            }
-                            _ => {}
+                        if let Some(ref table) = &self.encolsing {
+                            return table.borrow_mut().get_from_symbol(
+                                var_name,
+                                index,
+                                lines,
+                                level + 1,
+                            );
                         }
                     }
 
-                    return error(
+                    error(
                         &format!("Symbol '{}' does not exist!", var_name),
                         index,
                         lines,
-                    );
+                    )
                 }
             }
         } else {
             if level < 256 {
-                match &self.encolsing {
-                    Some(ref table) => {
-                        return table.borrow_mut().get_from_symbol(var_name, index, lines, level + 1);
-                    }
-                    _ => {}
+                if let Some(ref table) = &self.encolsing {
+                    return table
+                        .borrow_mut()
+                        .get_from_symbol(var_name, index, lines, level + 1);
                 }
             }
-            return error(
+            error(
                 &format!("Symbol '{}' does not exist!", var_name),
                 index,
                 lines,
-            );
+            )
         }
     }
 }
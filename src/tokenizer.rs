@@ -1,3 +1,4 @@
+use crate::badger_debug::{error, error_at};
 use std::fmt;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -7,9 +8,12 @@ pub enum TokenType {
 
     StringLiteral(String),
     NumberLiteral(f64),
+    IntegerLiteral(i64),
     BooleanLiteral(bool),
 
     Identifier(String),
+    // the text of a `##` line, which the parser attaches to the next declaration
+    DocComment(String),
 
     OpenParent,
     CloseParent,
@@ -20,6 +24,8 @@ pub enum TokenType {
 
     Colon,
     Period,
+    Range,
+    FatArrow,
     Comma,
     Query,
     Refference,
@@ -29,6 +35,14 @@ pub enum TokenType {
     Star,
     Slash,
     Mod,
+    Power,
+
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+    Tilde,
 
     Greater,
     GreaterEquals,
@@ -42,6 +56,13 @@ pub enum TokenType {
 
     Bang,
     Asign,
+    PlusAsign,
+    MinusAsign,
+    StarAsign,
+    SlashAsign,
+    ModAsign,
+    Increment,
+    Decrement,
 
     Null,
     True,
@@ -49,16 +70,27 @@ pub enum TokenType {
     If,
     Else,
     Fxn,
+    Struct,
+    Enum,
     While,
+    For,
+    In,
+    Match,
     Break,
     Continue,
+    Throw,
+    Try,
+    Catch,
+    Finally,
     Import,
     Export,
     Return,
     Num,
+    Int,
     Str,
     Bool,
     Var,
+    Const,
     IdentifierNotKeyword,
 }
 
@@ -74,11 +106,23 @@ pub struct Token {
     pub ttype: TokenType,
 }
 
-pub fn tokenize(src: String) -> (Vec<Token>, Vec<usize>) {
-    let mut tok = Vec::<Token>::new();
+pub fn tokenize(src: String) -> Result<(Vec<Token>, Vec<usize>), String> {
     let text: Vec<char> = src.chars().collect();
     let mut new_lines: Vec<usize> = Vec::<usize>::new();
-    let mut current: usize = 0;
+    let mut tok = scan(&text, 0, &mut new_lines)?;
+
+    tok.push(Token {
+        index: text.len(),
+        ttype: TokenType::Eof,
+    });
+
+    Ok((tok, new_lines))
+}
+
+/// Tokens for `text[from..]`. Indices stay relative to the whole source so lines are reported right.
+fn scan(text: &[char], from: usize, new_lines: &mut Vec<usize>) -> Result<Vec<Token>, String> {
+    let mut tok = Vec::<Token>::new();
+    let mut current: usize = from;
 
     while current < text.len() {
         match text[current] {
@@ -94,10 +138,20 @@ pub fn tokenize(src: String) -> (Vec<Token>, Vec<usize>) {
                 index: current,
                 ttype: TokenType::Colon,
             }),
-            '.' => tok.push(Token {
-                index: current,
-                ttype: TokenType::Period,
-            }),
+            '.' => {
+                if check(current + 1, text, '.', false) {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::Range,
+                    });
+                    current += 1;
+                } else {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::Period,
+                    })
+                }
+            }
             ',' => tok.push(Token {
                 index: current,
                 ttype: TokenType::Comma,
@@ -111,10 +165,25 @@ pub fn tokenize(src: String) -> (Vec<Token>, Vec<usize>) {
                 ttype: TokenType::Refference,
             }),
             '#' => {
-                while check(current, &text, '\n', true) {
-                    current = current + 1;
+                if check(current + 1, text, '[', false) {
+                    current = block_comment(text, current, new_lines)?;
+                } else {
+                    let start: usize = current;
+                    while check(current, text, '\n', true) {
+                        current += 1;
+                    }
+                    // `##` is a doc comment, while `###...` lines are left as plain comments
+                    if check(start + 1, text, '#', false) && check(start + 2, text, '#', true) {
+                        let line: String = substring(text, start + 2, current - start - 2);
+                        tok.push(Token {
+                            index: start,
+                            ttype: TokenType::DocComment(line.trim().to_string()),
+                        });
+                    }
+                    if current < text.len() {
+                        new_lines.push(current);
+                    }
                 }
-                new_lines.push(current);
             }
 
             '(' => tok.push(Token {
@@ -144,26 +213,94 @@ pub fn tokenize(src: String) -> (Vec<Token>, Vec<usize>) {
                 ttype: TokenType::CloseBrace,
             }),
 
-            '+' => tok.push(Token {
-                index: current,
-                ttype: TokenType::Plus,
-            }),
-            '-' => tok.push(Token {
-                index: current,
-                ttype: TokenType::Minus,
-            }),
-            '*' => tok.push(Token {
-                index: current,
-                ttype: TokenType::Star,
-            }),
-            '/' => tok.push(Token {
-                index: current,
-                ttype: TokenType::Slash,
-            }),
-            '%' => tok.push(Token {
-                index: current,
-                ttype: TokenType::Mod,
-            }),
+            '+' => {
+                if check(current + 1, text, '=', false) {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::PlusAsign,
+                    });
+                    current += 1;
+                } else if check(current + 1, text, '+', false) {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::Increment,
+                    });
+                    current += 1;
+                } else {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::Plus,
+                    })
+                }
+            }
+            '-' => {
+                if check(current + 1, text, '=', false) {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::MinusAsign,
+                    });
+                    current += 1;
+                } else if check(current + 1, text, '-', false) {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::Decrement,
+                    });
+                    current += 1;
+                } else {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::Minus,
+                    })
+                }
+            }
+            '*' => {
+                if check(current + 1, text, '*', false) {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::Power,
+                    });
+                    current += 1;
+                } else if check(current + 1, text, '=', false) {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::StarAsign,
+                    });
+                    current += 1;
+                } else {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::Star,
+                    })
+                }
+            }
+            '/' => {
+                if check(current + 1, text, '=', false) {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::SlashAsign,
+                    });
+                    current += 1;
+                } else {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::Slash,
+                    })
+                }
+            }
+            '%' => {
+                if check(current + 1, text, '=', false) {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::ModAsign,
+                    });
+                    current += 1;
+                } else {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::Mod,
+                    })
+                }
+            }
             '&' => tok.push(Token {
                 index: current,
                 ttype: TokenType::And,
@@ -174,12 +311,18 @@ pub fn tokenize(src: String) -> (Vec<Token>, Vec<usize>) {
             }),
 
             '>' => {
-                if check(current + 1, &text, '=', false) {
+                if check(current + 1, text, '>', false) {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::ShiftRight,
+                    });
+                    current += 1;
+                } else if check(current + 1, text, '=', false) {
                     tok.push(Token {
                         index: current,
                         ttype: TokenType::GreaterEquals,
                     });
-                    current = current + 1;
+                    current += 1;
                 } else {
                     tok.push(Token {
                         index: current,
@@ -188,12 +331,18 @@ pub fn tokenize(src: String) -> (Vec<Token>, Vec<usize>) {
                 }
             }
             '<' => {
-                if check(current + 1, &text, '=', false) {
+                if check(current + 1, text, '<', false) {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::ShiftLeft,
+                    });
+                    current += 1;
+                } else if check(current + 1, text, '=', false) {
                     tok.push(Token {
                         index: current,
                         ttype: TokenType::LesserEquals,
                     });
-                    current = current + 1;
+                    current += 1;
                 } else {
                     tok.push(Token {
                         index: current,
@@ -201,13 +350,17 @@ pub fn tokenize(src: String) -> (Vec<Token>, Vec<usize>) {
                     })
                 }
             }
+            '~' => tok.push(Token {
+                index: current,
+                ttype: TokenType::Tilde,
+            }),
             '!' => {
-                if check(current + 1, &text, '=', false) {
+                if check(current + 1, text, '=', false) {
                     tok.push(Token {
                         index: current,
                         ttype: TokenType::BangEquals,
                     });
-                    current = current + 1;
+                    current += 1;
                 } else {
                     tok.push(Token {
                         index: current,
@@ -216,12 +369,18 @@ pub fn tokenize(src: String) -> (Vec<Token>, Vec<usize>) {
                 }
             }
             '=' => {
-                if check(current + 1, &text, '=', false) {
+                if check(current + 1, text, '=', false) {
                     tok.push(Token {
                         index: current,
                         ttype: TokenType::Equality,
                     });
-                    current = current + 1;
+                    current += 1;
+                } else if check(current + 1, text, '>', false) {
+                    tok.push(Token {
+                        index: current,
+                        ttype: TokenType::FatArrow,
+                    });
+                    current += 1;
                 } else {
                     tok.push(Token {
                         index: current,
@@ -231,73 +390,26 @@ pub fn tokenize(src: String) -> (Vec<Token>, Vec<usize>) {
             }
 
             '\"' => {
-                let start: usize = current + 1;
-                let mut length: usize = 0;
-                let mut esc: bool = false;
-                current = current + 1;
-
-                while test_string(current, esc, &text) {
-                    if text[current] == '\n' {
-                        new_lines.push(current);
-                    }
-                    length = length + 1;
-                    esc = false;
-                    if text[current] == '\\' {
-                        esc = true;
-                    }
-                    current = current + 1;
-                }
-
-                //current = current + 1;
-
-                let s: String = substring(&text, start, length);
-                let value = parse_string_literal(&s);
-
-                tok.push(Token {
-                    index: start - 1,
-                    ttype: TokenType::StringLiteral(value),
-                });
+                current = string_literal(text, current, &mut tok, new_lines)?;
+            }
+            'r' if check(current + 1, text, '\"', false) => {
+                current = string_literal(text, current, &mut tok, new_lines)?;
             }
 
             '0'..='9' => {
-                let start: usize = current;
-                let mut length: usize = 0;
-
-                while test_number(current, &text) {
-                    current = current + 1;
-                    length = length + 1;
-                }
-
-                if check(current, &text, '.', false) {
-                    length = length + 1;
-                    current = current + 1;
-
-                    while test_number(current, &text) {
-                        current = current + 1;
-                        length = length + 1;
-                    }
-                } else {
-                    current = current - 1;
-                }
-                
-                let _lex_ = substring(&text, start, length);
-                let num: f64 = _lex_.clone().parse::<f64>().unwrap();
-                tok.push(Token {
-                    index: start,
-                    ttype: TokenType::NumberLiteral(num),
-                });
+                current = number_literal(text, current, &mut tok, new_lines)?;
             }
             _ => {
                 if text[current].is_alphabetic() || text[current] == '_' {
                     let start: usize = current;
                     let mut length: usize = 0;
 
-                    while test_identifier(current, &text) {
-                        current = current + 1;
-                        length = length + 1;
+                    while test_identifier(current, text) {
+                        current += 1;
+                        length += 1;
                     }
 
-                    let word: String = substring(&text, start, length);
+                    let word: String = substring(text, start, length);
 
                     if is_keyword(&word) {
                         tok.push(Token {
@@ -311,28 +423,389 @@ pub fn tokenize(src: String) -> (Vec<Token>, Vec<usize>) {
                         });
                     }
 
-                    current = current - 1;
+                    current -= 1;
                 }
             }
         }
 
-        current = current + 1;
+        current += 1;
+    }
+
+    Ok(tok)
+}
+
+/// Skips the `#[ ... ]#` comment starting at `start` and returns the index of its closing `#`.
+/// Block comments nest, so `#[ a #[ b ]# c ]#` is a single comment.
+fn block_comment(text: &[char], start: usize, new_lines: &mut Vec<usize>) -> Result<usize, String> {
+    let mut depth: usize = 0;
+    let mut current: usize = start;
+
+    while current < text.len() {
+        if text[current] == '#' && check(current + 1, text, '[', false) {
+            depth += 1;
+            current += 1;
+        } else if text[current] == ']' && check(current + 1, text, '#', false) {
+            depth -= 1;
+            current += 1;
+            if depth == 0 {
+                return Ok(current);
+            }
+        } else if text[current] == '\n' {
+            new_lines.push(current);
+        }
+        current += 1;
+    }
+
+    error("Unterminated block comment", &start, new_lines)
+}
+
+/// Reads the number starting at `start` and returns the index of its last character.
+/// Takes `0x`, `0b` and `0o` integers, `1.5e-3` style exponents and `_` between digits.
+fn number_literal(
+    text: &[char],
+    start: usize,
+    tok: &mut Vec<Token>,
+    new_lines: &[usize],
+) -> Result<usize, String> {
+    let radix: u32 = match (text[start], text.get(start + 1)) {
+        ('0', Some('x')) | ('0', Some('X')) => 16,
+        ('0', Some('b')) | ('0', Some('B')) => 2,
+        ('0', Some('o')) | ('0', Some('O')) => 8,
+        _ => 10,
+    };
+
+    let ttype: TokenType;
+    let mut current: usize;
+    if radix != 10 {
+        let (end, digits) = digits(text, start + 2, radix, new_lines)?;
+        if digits.is_empty() {
+            return error("Expected digits after the number prefix", &start, new_lines);
+        }
+        current = end;
+        ttype = match i64::from_str_radix(&digits, radix) {
+            Ok(n) => TokenType::IntegerLiteral(n),
+            Err(_) => return error("Integer literal is too large", &start, new_lines),
+        };
+    } else {
+        let (end, mut lexeme) = digits(text, start, 10, new_lines)?;
+        current = end;
+        let mut whole: bool = true;
+
+        // `1..10` is a range, not the number `1.`
+        if check(current, text, '.', false) && test_number(current + 1, text) {
+            let (end, fraction) = digits(text, current + 1, 10, new_lines)?;
+            lexeme = format!("{}.{}", lexeme, fraction);
+            current = end;
+            whole = false;
+        }
+
+        if check(current, text, 'e', false) || check(current, text, 'E', false) {
+            let mut at: usize = current + 1;
+            let mut sign: &str = "";
+            if check(at, text, '-', false) || check(at, text, '+', false) {
+                sign = if text[at] == '-' { "-" } else { "" };
+                at += 1;
+            }
+            let (end, exponent) = digits(text, at, 10, new_lines)?;
+            if exponent.is_empty() {
+                return error("Expected digits in the exponent", &current, new_lines);
+            }
+            lexeme = format!("{}e{}{}", lexeme, sign, exponent);
+            current = end;
+            whole = false;
+        }
+
+        ttype = if whole {
+            match lexeme.parse::<i64>() {
+                Ok(n) => TokenType::IntegerLiteral(n),
+                Err(_) => return error("Integer literal is too large", &start, new_lines),
+            }
+        } else {
+            match lexeme.parse::<f64>() {
+                Ok(n) if n.is_finite() => TokenType::NumberLiteral(n),
+                _ => return error("Number literal is too large", &start, new_lines),
+            }
+        };
+    }
+
+    // `12ab` or `0b102` is one bad number rather than a number and a name
+    if test_identifier(current, text) {
+        return error(
+            &format!("Invalid character '{}' in number literal", text[current]),
+            &current,
+            new_lines,
+        );
     }
 
     tok.push(Token {
-        index: text.len(),
-        ttype: TokenType::Eof,
+        index: start,
+        ttype,
     });
+    Ok(current - 1)
+}
 
-    return (tok, new_lines);
+/// Reads the digits of `radix` from `from`, leaving out the `_` separators.
+/// Returns the index after the last one along with the digits.
+fn digits(
+    text: &[char],
+    from: usize,
+    radix: u32,
+    new_lines: &[usize],
+) -> Result<(usize, String), String> {
+    let mut current: usize = from;
+    let mut found: String = String::new();
+
+    while current < text.len() && (text[current].is_digit(radix) || text[current] == '_') {
+        if text[current] == '_' {
+            let next_is_digit: bool = current + 1 < text.len() && text[current + 1].is_digit(radix);
+            if found.is_empty() || !next_is_digit {
+                return error("'_' can only be used between digits", &current, new_lines);
+            }
+        } else {
+            found.push(text[current]);
+        }
+        current += 1;
+    }
+
+    Ok((current, found))
 }
 
-pub fn is_keyword(word: &str) -> bool {
-    match word {
-        "true" | "false" | "if" | "else" | "fxn" | "while" | "break" | "continue" | "import"
-        | "return" | "num" | "str" | "bool" | "null" | "export" | "var" => true,
-        _ => false,
+/// Reads the string starting at `start` and returns the index of its last quote.
+/// `"a {x} b"` becomes the tokens of `("a " + (x) + " b")`; `{{` and `}}` stand for braces.
+/// `"""` strings can hold `"` and span lines, and raw `r"..."` strings take everything as written.
+fn string_literal(
+    text: &[char],
+    start: usize,
+    tok: &mut Vec<Token>,
+    new_lines: &mut Vec<usize>,
+) -> Result<usize, String> {
+    let raw: bool = text[start] == 'r';
+    let open: usize = if raw { start + 1 } else { start };
+    let triple: bool = check(open + 1, text, '"', false) && check(open + 2, text, '"', false);
+
+    let mut pieces: Vec<Token> = Vec::<Token>::new();
+    let mut part: String = "".to_string();
+    let mut part_start: usize = start;
+    let mut current: usize = if triple { open + 3 } else { open + 1 };
+
+    // a line break right after the opening `"""` is not part of the string
+    if triple && check(current, text, '\n', false) {
+        new_lines.push(current);
+        current += 1;
     }
+
+    loop {
+        if current >= text.len() {
+            return error("Unterminated string", &start, new_lines);
+        }
+        let c = text[current];
+        if c == '\n' {
+            new_lines.push(current);
+        }
+        if c == '"' {
+            if !triple {
+                break;
+            }
+            if check(current + 1, text, '"', false) && check(current + 2, text, '"', false) {
+                current += 2;
+                break;
+            }
+        }
+        if raw {
+            part.push(c);
+            current += 1;
+            continue;
+        }
+        if c == '\\' {
+            let (decoded, next) = escape(text, current, new_lines)?;
+            part.push(decoded);
+            current = next;
+            continue;
+        }
+        if c == '{' && check(current + 1, text, '{', false) {
+            part.push('{');
+            current += 2;
+            continue;
+        }
+        if c == '}' && check(current + 1, text, '}', false) {
+            part.push('}');
+            current += 2;
+            continue;
+        }
+        if c == '}' {
+            return error("Unmatched '}' in string, use '}}'", &current, new_lines);
+        }
+        if c == '{' {
+            let end = interpolation_end(text, current, new_lines)?;
+            let code = scan(&text[..end], current + 1, new_lines)?;
+            if code.is_empty() {
+                return error("Empty expression in string interpolation", &current, new_lines);
+            }
+
+            pieces.push(Token {
+                index: part_start,
+                ttype: TokenType::StringLiteral(part),
+            });
+            for (ttype, at) in [(TokenType::Plus, current), (TokenType::OpenParent, current)] {
+                pieces.push(Token { index: at, ttype });
+            }
+            pieces.extend(code);
+            for (ttype, at) in [(TokenType::CloseParent, end), (TokenType::Plus, end)] {
+                pieces.push(Token { index: at, ttype });
+            }
+
+            part = "".to_string();
+            part_start = end;
+            current = end + 1;
+            continue;
+        }
+        part.push(c);
+        current += 1;
+    }
+
+    let last = Token {
+        index: part_start,
+        ttype: TokenType::StringLiteral(part),
+    };
+    if pieces.is_empty() {
+        tok.push(last);
+        return Ok(current);
+    }
+    tok.push(Token {
+        index: start,
+        ttype: TokenType::OpenParent,
+    });
+    tok.extend(pieces);
+    tok.push(last);
+    tok.push(Token {
+        index: current,
+        ttype: TokenType::CloseParent,
+    });
+    Ok(current)
+}
+
+/// Decodes the escape whose `\` is at `at`, giving the character and the index after the escape.
+fn escape(text: &[char], at: usize, new_lines: &[usize]) -> Result<(char, usize), String> {
+    let simple = match text.get(at + 1) {
+        Some('n') => '\n',
+        Some('t') => '\t',
+        Some('r') => '\r',
+        Some('0') => '\0',
+        Some(c @ ('\\' | '"' | '\'' | '{' | '}')) => *c,
+        Some('x') => {
+            let digits: String = text.iter().skip(at + 2).take(2).collect();
+            // `from_str_radix` alone would also take a sign, as in `\x+1`
+            if digits.len() != 2 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                return error_at("Expected two hex digits after '\\x'", &at, new_lines);
+            }
+            return match u8::from_str_radix(&digits, 16) {
+                Ok(n) if n <= 0x7F => Ok((n as char, at + 4)),
+                _ => error_at(
+                    "'\\x' escapes go up to 7F, use '\\u{...}' for other characters",
+                    &at,
+                    new_lines,
+                ),
+            };
+        }
+        Some('u') => return unicode_escape(text, at, new_lines),
+        Some(c) if !c.is_whitespace() => {
+            return error_at(&format!("Invalid escape '\\{}' in string", c), &at, new_lines)
+        }
+        _ => return error_at("Invalid escape '\\' in string", &at, new_lines),
+    };
+    Ok((simple, at + 2))
+}
+
+/// Decodes `\u{...}`, which holds one to six hex digits naming a Unicode character.
+fn unicode_escape(text: &[char], at: usize, new_lines: &[usize]) -> Result<(char, usize), String> {
+    if !check(at + 2, text, '{', false) {
+        return error_at("Expected '{' after '\\u'", &at, new_lines);
+    }
+    let mut current: usize = at + 3;
+    let mut digits: String = "".to_string();
+    while current < text.len() && text[current].is_ascii_hexdigit() {
+        digits.push(text[current]);
+        current += 1;
+    }
+    if !check(current, text, '}', false) {
+        return error_at("Expected '}' to close the '\\u{' escape", &at, new_lines);
+    }
+    if digits.is_empty() || digits.len() > 6 {
+        return error_at("'\\u{...}' takes one to six hex digits", &at, new_lines);
+    }
+    match u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32) {
+        Some(c) => Ok((c, current + 1)),
+        None => error_at(
+            &format!("'\\u{{{}}}' is not a valid Unicode character", digits),
+            &at,
+            new_lines,
+        ),
+    }
+}
+
+/// Index of the `}` closing the interpolation opened at `open`, skipping strings nested inside it.
+fn interpolation_end(text: &[char], open: usize, new_lines: &[usize]) -> Result<usize, String> {
+    let mut depth: usize = 0;
+    let mut at: usize = open;
+    while at < text.len() {
+        match text[at] {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(at);
+                }
+            }
+            '"' => {
+                at += 1;
+                while at < text.len() && text[at] != '"' {
+                    if text[at] == '\\' {
+                        at += 1;
+                    }
+                    at += 1;
+                }
+            }
+            _ => {}
+        }
+        at += 1;
+    }
+    error("Unterminated '{' in string", &open, new_lines)
+}
+
+pub fn is_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "true"
+            | "false"
+            | "if"
+            | "else"
+            | "fxn"
+            | "struct"
+            | "enum"
+            | "while"
+            | "for"
+            | "in"
+            | "match"
+            | "break"
+            | "continue"
+            | "throw"
+            | "try"
+            | "catch"
+            | "finally"
+            | "import"
+            | "return"
+            | "num"
+            | "int"
+            | "str"
+            | "bool"
+            | "null"
+            | "export"
+            | "var"
+            | "const"
+            | "band"
+            | "bor"
+            | "bxor"
+    )
 }
 
 pub fn get_keyword(word: &str) -> TokenType {
@@ -342,34 +815,48 @@ pub fn get_keyword(word: &str) -> TokenType {
         "if" => TokenType::If,
         "else" => TokenType::Else,
         "fxn" => TokenType::Fxn,
+        "struct" => TokenType::Struct,
+        "enum" => TokenType::Enum,
         "while" => TokenType::While,
+        "for" => TokenType::For,
+        "in" => TokenType::In,
+        "match" => TokenType::Match,
         "break" => TokenType::Break,
         "continue" => TokenType::Continue,
+        "throw" => TokenType::Throw,
+        "try" => TokenType::Try,
+        "catch" => TokenType::Catch,
+        "finally" => TokenType::Finally,
         "import" => TokenType::Import,
         "return" => TokenType::Return,
         "num" => TokenType::Num,
+        "int" => TokenType::Int,
         "str" => TokenType::Str,
         "bool" => TokenType::Bool,
         "null" => TokenType::Null,
         "export" => TokenType::Export,
         "var" => TokenType::Var,
+        "const" => TokenType::Const,
+        "band" => TokenType::BitAnd,
+        "bor" => TokenType::BitOr,
+        "bxor" => TokenType::BitXor,
         _ => TokenType::IdentifierNotKeyword,
     }
 }
 
-fn check(index: usize, text: &Vec<char>, test: char, neg: bool) -> bool {
+fn check(index: usize, text: &[char], test: char, neg: bool) -> bool {
     if index < text.len() {
         if !neg {
-            return text[index] == test;
+            text[index] == test
         } else {
-            return text[index] != test;
+            text[index] != test
         }
     } else {
         false
     }
 }
 
-fn test_identifier(current: usize, text: &Vec<char>) -> bool {
+fn test_identifier(current: usize, text: &[char]) -> bool {
     if current < text.len() {
         text[current].is_alphanumeric() || text[current] == '_'
     } else {
@@ -377,54 +864,20 @@ fn test_identifier(current: usize, text: &Vec<char>) -> bool {
     }
 }
 
-fn test_number(current: usize, text: &Vec<char>) -> bool {
-    if current < text.len() {
-        return text[current].is_digit(10) && text[current] != ' ';
-    } else {
-        false
-    }
-}
-
-fn test_string(current: usize, esc: bool, text: &Vec<char>) -> bool {
+fn test_number(current: usize, text: &[char]) -> bool {
     if current < text.len() {
-        esc || text[current] != '"'
+        text[current].is_ascii_digit() && text[current] != ' '
     } else {
         false
     }
 }
 
-fn substring(source: &Vec<char>, start: usize, length: usize) -> String {
+fn substring(source: &[char], start: usize, length: usize) -> String {
     let mut sub: String = "".to_string();
     let mut i: usize = start;
     while i < start + length {
         sub.push(source[i]);
-        i = i + 1;
-    }
-    return sub;
-}
-
-fn parse_string_literal(src: &str) -> String {
-    let mut ret: String = "".to_string();
-    let mut esc: bool = false;
-
-    for c in src.chars() {
-        if esc {
-            match c {
-                'n' => ret.push('\n'),
-                't' => ret.push('\t'),
-
-                _ => ret.push(c),
-            }
-            esc = false;
-            continue;
-        }
-
-        if c == '\\' {
-            esc = true;
-        } else {
-            ret.push(c);
-        }
+        i += 1;
     }
-
-    return ret;
+    sub
 }
@@ -0,0 +1,1062 @@
+use crate::{
+    badger_debug::{error_struct, get_col, get_line_from_index},
+    builtins::global_table,
+    enums::EnumDecl,
+    expression::{Expression, Value},
+    function::FunctionDecl,
+    module::module_name,
+    statement::{MatchArm, Pattern, Statement},
+    structs::StructDecl,
+    tokenizer::{Token, TokenType},
+    var_type::VarType,
+};
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+#[derive(Clone)]
+struct Symbol {
+    // what the runtime lets the slot hold, as in `SymbolTable::set_var_val`
+    slot: VarType,
+    // what is known about the value it holds right now
+    known: VarType,
+    // the declaration of a named function, to check calls against
+    fxn: Option<Rc<FunctionDecl>>,
+    constant: bool,
+    // a `var` typed by its first value, whose ints widen to nums when it is given or used as one
+    widens: bool,
+}
+
+pub struct TypeChecker<'a> {
+    pub source: &'a Vec<Option<Statement>>,
+    pub lines: &'a Vec<usize>,
+    pub errors: Vec<String>,
+    scopes: Vec<HashMap<String, Symbol>>,
+    structs: HashMap<String, Rc<StructDecl>>,
+    enums: HashMap<String, Rc<EnumDecl>>,
+    // name and return type of each function being checked, innermost last
+    returns: Vec<(String, Option<VarType>)>,
+    // named functions of each open block, checked once the block is done so they see all of it
+    pending: Vec<Vec<Rc<FunctionDecl>>>,
+}
+
+impl<'a> TypeChecker<'a> {
+    pub fn new(source: &'a Vec<Option<Statement>>, lines: &'a Vec<usize>) -> TypeChecker<'a> {
+        let mut builtins: HashMap<String, Symbol> = HashMap::new();
+        if let Some(parent) = &global_table().encolsing {
+            for (name, vtype) in &parent.borrow().types {
+                builtins.insert(name.clone(), Symbol::plain(vtype.clone()));
+            }
+        }
+        let mut structs: HashMap<String, Rc<StructDecl>> = HashMap::new();
+        structs.insert("Error".to_owned(), error_struct());
+
+        TypeChecker {
+            source,
+            lines,
+            errors: Vec::<String>::new(),
+            scopes: vec![builtins],
+            structs,
+            enums: HashMap::new(),
+            returns: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Checks the whole script without running it and returns every error found.
+    pub fn check(&mut self) -> Result<(), Vec<String>> {
+        let source = self.source;
+        self.scopes.push(HashMap::new());
+        self.check_statements(source.iter().flatten());
+        self.scopes.pop();
+
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        Err(std::mem::take(&mut self.errors))
+    }
+
+    fn check_statements<'s>(&mut self, statements: impl Iterator<Item = &'s Statement>) {
+        self.pending.push(Vec::new());
+        for stmt in statements {
+            self.check_statement(stmt);
+        }
+        for decl in self.pending.pop().unwrap_or_default() {
+            self.check_fxn(&decl);
+        }
+    }
+
+    fn check_block<'s>(&mut self, statements: impl Iterator<Item = &'s Statement>) {
+        self.scopes.push(HashMap::new());
+        self.check_statements(statements);
+        self.scopes.pop();
+    }
+
+    fn check_fxn(&mut self, decl: &Rc<FunctionDecl>) {
+        let mut scope: HashMap<String, Symbol> = HashMap::new();
+        for param in &decl.params {
+            scope.insert(param.name.clone(), Symbol::plain(param.ptype.clone()));
+        }
+        self.scopes.push(scope);
+        self.returns
+            .push((decl.name.clone(), decl.return_type.clone()));
+        self.check_statements(decl.body.iter());
+        self.returns.pop();
+        self.scopes.pop();
+    }
+
+    fn check_statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Expr(expr) => {
+                self.type_of(expr);
+            }
+            Statement::Return(expr) => {
+                let actual = self.type_of(expr);
+                if let Some((name, Some(rtype))) = self.returns.last().cloned() {
+                    if !self.accepts(&rtype, expr, &actual) {
+                        let msg = format!("Function '{}' must return a value of type ({})", name, rtype);
+                        self.report(&msg, index_of(expr));
+                    }
+                }
+            }
+            Statement::Block(statements) => self.check_block(statements.iter()),
+            Statement::IfStmt(cond, then, otherwise) => {
+                self.type_of(cond);
+                self.check_block(std::iter::once(then.as_ref()));
+                if let Some(other) = otherwise.as_ref() {
+                    self.check_block(std::iter::once(other));
+                }
+            }
+            Statement::WhileStmt(cond, body) => {
+                self.type_of(cond);
+                self.check_block(std::iter::once(body.as_ref()));
+            }
+            Statement::ForStmt(init, cond, step, body) => {
+                self.scopes.push(HashMap::new());
+                if let Some(init_stmt) = init.as_ref() {
+                    self.check_statement(init_stmt);
+                }
+                if let Some(c) = cond {
+                    self.type_of(c);
+                }
+                if let Some(s) = step {
+                    self.type_of(s);
+                }
+                self.check_block(std::iter::once(body.as_ref()));
+                self.scopes.pop();
+            }
+            Statement::ForEach(name, collection, body, index) => {
+                let item = match strip(&self.type_of(collection)) {
+                    VarType::Array(elem) => *elem,
+                    VarType::Map(_) | VarType::Str => VarType::Str,
+                    VarType::Var => VarType::Var,
+                    _ => {
+                        self.report("Can only loop over arrays, maps and strings", *index);
+                        VarType::Var
+                    }
+                };
+                let mut scope: HashMap<String, Symbol> = HashMap::new();
+                scope.insert(name.clone(), Symbol::inferred(VarType::Var, item));
+                self.scopes.push(scope);
+                self.check_block(std::iter::once(body.as_ref()));
+                self.scopes.pop();
+            }
+            Statement::Match(subject, arms, index) => {
+                let subject_type = self.type_of(subject);
+                self.check_patterns(subject, &subject_type, arms, *index);
+                for arm in arms {
+                    let mut scope: HashMap<String, Symbol> = HashMap::new();
+                    if let [Pattern::Variant(enum_name, variant, names, _)] = arm.patterns.as_slice() {
+                        let payload = self.variant_payload(enum_name, variant);
+                        for (i, name) in names.iter().enumerate() {
+                            let known = payload
+                                .as_ref()
+                                .and_then(|p| p.get(i).cloned())
+                                .unwrap_or(VarType::Var);
+                            scope.insert(name.clone(), Symbol::inferred(VarType::Var, known));
+                        }
+                    }
+                    self.scopes.push(scope);
+                    self.check_block(std::iter::once(&arm.body));
+                    self.scopes.pop();
+                }
+            }
+            Statement::VarDecl(name, init, vtype, index)
+            | Statement::ConstDecl(name, init, vtype, index) => {
+                let actual = self.type_of(init);
+                if !self.accepts(vtype, init, &actual) {
+                    let msg = format!("Expression is not of expected type ({})", vtype);
+                    self.report(&msg, *index);
+                }
+                let constant = matches!(stmt, Statement::ConstDecl(..));
+                let symbol = match vtype {
+                    // a `var` keeps the type of the value it was first given
+                    VarType::Var => Symbol {
+                        widens: !constant,
+                        ..Symbol::inferred(slot_for(&actual), actual)
+                    },
+                    _ => Symbol::plain(vtype.clone()),
+                };
+                let symbol = Symbol { constant, ..symbol };
+                self.declare(name, symbol, *index);
+            }
+            Statement::FxnDecl(decl) => {
+                let symbol = Symbol {
+                    slot: VarType::Fxn,
+                    known: VarType::Fxn,
+                    fxn: Some(Rc::clone(decl)),
+                    constant: false,
+                    widens: false,
+                };
+                self.declare(&decl.name, symbol, decl.index);
+                if let Some(open) = self.pending.last_mut() {
+                    open.push(Rc::clone(decl));
+                }
+            }
+            Statement::StructDecl(decl) => {
+                self.structs.insert(decl.name.clone(), Rc::clone(decl));
+                self.declare(&decl.name, Symbol::plain(VarType::Var), decl.index);
+            }
+            Statement::EnumDecl(decl) => {
+                self.enums.insert(decl.name.clone(), Rc::clone(decl));
+                self.declare(&decl.name, Symbol::plain(VarType::Var), decl.index);
+            }
+            Statement::Break(_) | Statement::Continue(_) => {}
+            Statement::Throw(expr, _index) => {
+                self.type_of(expr);
+            }
+            Statement::Try(body, handler, finally, _index) => {
+                self.check_block(std::iter::once(body.as_ref()));
+                if let Some((name, catch_body)) = handler {
+                    let mut scope: HashMap<String, Symbol> = HashMap::new();
+                    scope.insert(name.clone(), Symbol::plain(VarType::Var));
+                    self.scopes.push(scope);
+                    self.check_block(std::iter::once(catch_body.as_ref()));
+                    self.scopes.pop();
+                }
+                if let Some(stmt) = finally.as_ref() {
+                    self.check_block(std::iter::once(stmt));
+                }
+            }
+            // importing the same module twice is allowed
+            Statement::Import(path, _index) => {
+                let name = module_name(Path::new(path));
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope
+                        .entry(name)
+                        .or_insert_with(|| Symbol::plain(VarType::Var));
+                }
+            }
+            Statement::Export(inner) => self.check_statement(inner),
+            Statement::Doc(_, inner) => self.check_statement(inner),
+        }
+    }
+
+    /// The static type of `expr`, reporting what is wrong inside it. `var` stands for unknown.
+    fn type_of(&mut self, expr: &Expression) -> VarType {
+        match expr {
+            Expression::SpecialSymbol(_, _) => VarType::Var,
+            Expression::Literal(val, _) => type_of_value(val),
+            Expression::Variable(name, index) | Expression::Reference(name, index) => {
+                match self.lookup(name) {
+                    Some(symbol) => symbol.known.clone(),
+                    None => {
+                        self.undefined(name, *index);
+                        VarType::Var
+                    }
+                }
+            }
+            Expression::Group(inner) => self.type_of(inner),
+            Expression::Query(inner, _) => {
+                self.type_of(inner);
+                VarType::Bool
+            }
+            Expression::Unary(sign, val) => {
+                let operand = self.type_of(val);
+                match (&sign.ttype, strip(&operand)) {
+                    (TokenType::Bang, _) => VarType::Bool,
+                    (TokenType::Tilde, VarType::Num | VarType::Int | VarType::Var) => VarType::Int,
+                    (_, t @ (VarType::Num | VarType::Int | VarType::Var)) => t,
+                    (_, other) => {
+                        let msg = format!(
+                            "Cannot use '{}' on a value of type ({})",
+                            operator_symbol(&sign.ttype),
+                            other
+                        );
+                        self.report(&msg, sign.index);
+                        VarType::Var
+                    }
+                }
+            }
+            Expression::Binary(left, sign, right) => {
+                let l = self.type_of(left);
+                let r = self.type_of(right);
+                self.operation(&l, sign, &r)
+            }
+            Expression::Assignment(name, rhs, index) => {
+                let actual = self.type_of(rhs);
+                self.pin(rhs);
+                self.assign(name, &actual, *index);
+                actual
+            }
+            Expression::CompoundAssignment(target, sign, rhs) => {
+                let current = self.type_of(target);
+                let operand = self.type_of(rhs);
+                let result = self.operation(&current, sign, &operand);
+                match target.as_ref() {
+                    Expression::Variable(name, index) => self.assign(name, &result, *index),
+                    _ => {
+                        if !self.accepts(&current, rhs, &result) {
+                            let msg = format!(
+                                "Cannot store a value of type ({}) where ({}) is expected",
+                                result, current
+                            );
+                            self.report(&msg, sign.index);
+                        }
+                    }
+                }
+                result
+            }
+            Expression::Call(callee, args, index) => self.call(callee, args, *index),
+            Expression::ArrayLiteral(items, _) => {
+                let mut elem: Option<VarType> = None;
+                for item in items {
+                    let t = self.type_of(item);
+                    self.pin(item);
+                    elem = Some(match elem {
+                        Some(e) => common(&e, &t),
+                        None => t,
+                    });
+                }
+                VarType::Array(Box::new(elem.unwrap_or(VarType::Var)))
+            }
+            Expression::MapLiteral(entries, index) => {
+                let mut elem: Option<VarType> = None;
+                for (key, val) in entries {
+                    let k = self.type_of(key);
+                    if !fits(&VarType::Str, &k) {
+                        self.report("Map keys must be strings", *index);
+                    }
+                    let t = self.type_of(val);
+                    self.pin(val);
+                    elem = Some(match elem {
+                        Some(e) => common(&e, &t),
+                        None => t,
+                    });
+                }
+                VarType::Map(Box::new(elem.unwrap_or(VarType::Var)))
+            }
+            Expression::Index(collection, position, index) => {
+                let c = self.type_of(collection);
+                let p = self.type_of(position);
+                self.element(&c, &p, *index)
+            }
+            Expression::IndexAssignment(collection, position, rhs, index) => {
+                let c = self.type_of(collection);
+                let p = self.type_of(position);
+                let elem = self.element(&c, &p, *index);
+                let actual = self.type_of(rhs);
+                if !self.accepts(&elem, rhs, &actual) {
+                    let msg = format!("Cannot store a value of type ({}) in a {}", actual, c);
+                    self.report(&msg, *index);
+                }
+                actual
+            }
+            Expression::Get(obj, name, index) => {
+                if let Some(enum_name) = self.enum_named(obj) {
+                    match self.variant_payload(&enum_name, name) {
+                        Some(payload) if !payload.is_empty() => {
+                            let msg = format!(
+                                "Variant '{}.{}' needs its values, as in '{}.{}(...)'",
+                                enum_name, name, enum_name, name
+                            );
+                            self.report(&msg, *index);
+                        }
+                        Some(_) => {}
+                        None => {
+                            let msg = format!("'{}' has no variant named '{}'", enum_name, name);
+                            self.report(&msg, *index);
+                        }
+                    }
+                    return VarType::Struct(enum_name);
+                }
+                let owner = self.type_of(obj);
+                self.field(&owner, name, *index)
+            }
+            Expression::SetField(obj, name, rhs, index) => {
+                let owner = self.type_of(obj);
+                let ftype = self.field(&owner, name, *index);
+                let actual = self.type_of(rhs);
+                if !self.accepts(&ftype, rhs, &actual) {
+                    let msg = format!(
+                        "Field '{}' of '{}' is not of expected type ({})",
+                        name, owner, ftype
+                    );
+                    self.report(&msg, *index);
+                }
+                actual
+            }
+            Expression::StructLiteral(name, fields, index) => {
+                let decl = self.structs.get(name).cloned();
+                let mut given: Vec<&String> = Vec::new();
+                for (field_name, val) in fields {
+                    let actual = self.type_of(val);
+                    let decl = match &decl {
+                        Some(d) => d,
+                        None => continue,
+                    };
+                    if given.contains(&field_name) {
+                        let msg = format!("Field '{}' is given more than once", field_name);
+                        self.report(&msg, *index);
+                    }
+                    given.push(field_name);
+                    match decl.fields.iter().find(|f| f.name == *field_name) {
+                        Some(f) if !self.accepts(&f.ftype, val, &actual) => {
+                            let msg = format!(
+                                "Field '{}' of '{}' is not of expected type ({})",
+                                f.name, decl.name, f.ftype
+                            );
+                            self.report(&msg, *index);
+                        }
+                        Some(_) => {}
+                        None => {
+                            let msg = format!("'{}' has no field named '{}'", decl.name, field_name);
+                            self.report(&msg, *index);
+                        }
+                    }
+                }
+                match &decl {
+                    Some(d) => {
+                        for f in &d.fields {
+                            let optional = matches!(f.ftype, VarType::Nullable(_));
+                            if !optional && !given.contains(&&f.name) {
+                                let msg = format!("Missing field '{}' for '{}'", f.name, d.name);
+                                self.report(&msg, *index);
+                            }
+                        }
+                    }
+                    None if self.lookup(name).is_none() => self.undefined(name, *index),
+                    None => {}
+                }
+                VarType::Struct(name.clone())
+            }
+            Expression::Lambda(decl) => {
+                self.check_fxn(decl);
+                VarType::Fxn
+            }
+            Expression::Convert(vtype, val, index) => {
+                let actual = self.type_of(val);
+                match strip(&actual) {
+                    VarType::Num | VarType::Int | VarType::Str | VarType::Var => {}
+                    other => {
+                        let msg = format!("Cannot convert a value of type ({}) to {}", other, vtype);
+                        self.report(&msg, *index);
+                    }
+                }
+                vtype.clone()
+            }
+        }
+    }
+
+    /// The type `left op right` gives, mirroring `binary_operation`.
+    fn operation(&mut self, left: &VarType, sign: &Token, right: &VarType) -> VarType {
+        let (l, r) = (strip(left), strip(right));
+        let numeric = |t: &VarType| matches!(t, VarType::Num | VarType::Int);
+        let arithmetic = if l == VarType::Int && r == VarType::Int {
+            VarType::Int
+        } else {
+            VarType::Num
+        };
+        let unknown = l == VarType::Var || r == VarType::Var;
+
+        let result = match sign.ttype {
+            TokenType::Plus if l == VarType::Str => Some(VarType::Str),
+            TokenType::Plus if numeric(&l) && r == VarType::Str => Some(VarType::Str),
+            TokenType::Star if l == VarType::Str && (numeric(&r) || r == VarType::Var) => {
+                Some(VarType::Str)
+            }
+            TokenType::Plus
+            | TokenType::Minus
+            | TokenType::Star
+            | TokenType::Mod
+            | TokenType::Power => {
+                if unknown {
+                    Some(VarType::Var)
+                } else if numeric(&l) && numeric(&r) {
+                    Some(arithmetic)
+                } else {
+                    None
+                }
+            }
+            TokenType::Slash => match unknown || (numeric(&l) && numeric(&r)) {
+                true => Some(VarType::Num),
+                false => None,
+            },
+            // whole `num`s are allowed too, but the result is always an `int`
+            TokenType::BitAnd
+            | TokenType::BitOr
+            | TokenType::BitXor
+            | TokenType::ShiftLeft
+            | TokenType::ShiftRight => {
+                let whole = |t: &VarType| numeric(t) || *t == VarType::Var;
+                match whole(&l) && whole(&r) {
+                    true => Some(VarType::Int),
+                    false => None,
+                }
+            }
+            TokenType::Greater
+            | TokenType::GreaterEquals
+            | TokenType::Lesser
+            | TokenType::LesserEquals => {
+                let ordered = (numeric(&l) && numeric(&r)) || (l == VarType::Str && r == VarType::Str);
+                match unknown || ordered {
+                    true => Some(VarType::Bool),
+                    false => None,
+                }
+            }
+            TokenType::Equality | TokenType::BangEquals => match comparable(&l, &r) {
+                true => Some(VarType::Bool),
+                false => None,
+            },
+            TokenType::And | TokenType::Or => {
+                let logical = |t: &VarType| matches!(t, VarType::Bool | VarType::Var);
+                match logical(&l) && logical(&r) {
+                    true => Some(VarType::Bool),
+                    false => None,
+                }
+            }
+            _ => Some(VarType::Var),
+        };
+
+        match result {
+            Some(t) => t,
+            None => {
+                let msg = format!(
+                    "Operator '{}' is not defined for ({}) and ({})",
+                    operator_symbol(&sign.ttype),
+                    l,
+                    r
+                );
+                self.report(&msg, sign.index);
+                VarType::Var
+            }
+        }
+    }
+
+    fn call(&mut self, callee: &Expression, args: &[Expression], index: usize) -> VarType {
+        let arg_types: Vec<VarType> = args.iter().map(|a| self.type_of(a)).collect();
+
+        // `Enum.Variant(...)` builds a variant
+        if let Expression::Get(obj, variant, _) = callee {
+            if let Some(enum_name) = self.enum_named(obj) {
+                match self.variant_payload(&enum_name, variant) {
+                    Some(payload) if payload.len() != arg_types.len() => {
+                        let msg = format!(
+                            "Variant '{}.{}' takes {} values but got {}",
+                            enum_name,
+                            variant,
+                            payload.len(),
+                            arg_types.len()
+                        );
+                        self.report(&msg, index);
+                    }
+                    Some(payload) => {
+                        for (i, (expected, (arg, actual))) in
+                            payload.iter().zip(args.iter().zip(&arg_types)).enumerate()
+                        {
+                            if !self.accepts(expected, arg, actual) {
+                                let msg = format!(
+                                    "Value {} of '{}.{}' is not of expected type ({})",
+                                    i + 1,
+                                    enum_name,
+                                    variant,
+                                    expected
+                                );
+                                self.report(&msg, index);
+                            }
+                        }
+                    }
+                    None => {
+                        let msg = format!("'{}' has no variant named '{}'", enum_name, variant);
+                        self.report(&msg, index);
+                    }
+                }
+                return VarType::Struct(enum_name);
+            }
+        }
+
+        let decl = match callee {
+            Expression::Variable(name, _) => self.lookup(name).and_then(|s| s.fxn.clone()),
+            _ => None,
+        };
+        let callee_type = self.type_of(callee);
+        let decl = match decl {
+            Some(d) => d,
+            None => {
+                if !matches!(strip(&callee_type), VarType::Fxn | VarType::Var) {
+                    let msg = format!("Cannot call a value of type ({})", callee_type);
+                    self.report(&msg, index);
+                }
+                return VarType::Var;
+            }
+        };
+
+        if decl.params.len() != arg_types.len() {
+            let msg = format!(
+                "Function '{}' expects {} arguments but got {}",
+                decl.name,
+                decl.params.len(),
+                arg_types.len()
+            );
+            self.report(&msg, index);
+        }
+        for (param, (arg, actual)) in decl.params.iter().zip(args.iter().zip(&arg_types)) {
+            if !self.accepts(&param.ptype, arg, actual) {
+                let msg = format!(
+                    "Argument '{}' of function '{}' is not of expected type ({})",
+                    param.name, decl.name, param.ptype
+                );
+                self.report(&msg, index);
+            }
+        }
+        decl.return_type.clone().unwrap_or(VarType::Var)
+    }
+
+    fn assign(&mut self, name: &str, actual: &VarType, index: usize) {
+        let symbol = match self.lookup_mut(name) {
+            Some(s) => s,
+            None => {
+                self.undefined(name, index);
+                return;
+            }
+        };
+        if symbol.constant {
+            let msg = format!("Cannot assign to constant '{}'", name);
+            self.report(&msg, index);
+            return;
+        }
+        if symbol.widens && !fits(&symbol.slot, actual) && fits(&symbol.slot.widened(), actual) {
+            symbol.slot = symbol.slot.widened();
+        }
+        let slot = symbol.slot.clone();
+        if fits(&slot, actual) {
+            if symbol.known != *actual {
+                symbol.known = slot;
+            }
+            symbol.fxn = None;
+            return;
+        }
+        let msg = match is_null(actual) {
+            true => format!("Cannot assign null to '{}' of non-nullable type ({})", name, slot),
+            false => format!(
+                "Cannot assign a value of type ({}) to '{}' of type ({})",
+                actual, name, slot
+            ),
+        };
+        self.report(&msg, index);
+    }
+
+    /// Whether `expr`, of type `actual`, may be stored where `declared` is expected.
+    /// Arrays and maps are shared, so one holding ints only fits a `num` one when nothing
+    /// else can see it: a new literal, or a `var` whose type then widens to match.
+    fn accepts(&mut self, declared: &VarType, expr: &Expression, actual: &VarType) -> bool {
+        if fits(declared, actual) {
+            self.pin(expr);
+            return true;
+        }
+        if !fits(declared, &actual.widened()) {
+            return false;
+        }
+        match expr {
+            Expression::ArrayLiteral(_, _) | Expression::MapLiteral(_, _) => true,
+            Expression::Variable(name, _) => match self.lookup_mut(name) {
+                Some(symbol) if symbol.widens => {
+                    symbol.known = symbol.known.widened();
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Stops a `var` holding an array or map from widening once the container is shared.
+    fn pin(&mut self, expr: &Expression) {
+        if let Expression::Variable(name, _) = expr {
+            if let Some(symbol) = self.lookup_mut(name) {
+                if matches!(symbol.known, VarType::Array(_) | VarType::Map(_)) {
+                    symbol.widens = false;
+                }
+            }
+        }
+    }
+
+    /// The type read by indexing `collection` with `position`.
+    fn element(&mut self, collection: &VarType, position: &VarType, index: usize) -> VarType {
+        let key = strip(position);
+        match strip(collection) {
+            VarType::Array(elem) => {
+                if !matches!(key, VarType::Int | VarType::Num | VarType::Var) {
+                    self.report("Index must be an 'int' or a 'num'", index);
+                }
+                *elem
+            }
+            VarType::Str => {
+                if !matches!(key, VarType::Int | VarType::Num | VarType::Var) {
+                    self.report("Index must be an 'int' or a 'num'", index);
+                }
+                VarType::Str
+            }
+            VarType::Map(elem) => {
+                if !matches!(key, VarType::Str | VarType::Var) {
+                    self.report("Map keys must be strings", index);
+                }
+                *elem
+            }
+            VarType::Var => VarType::Var,
+            other => {
+                let msg = format!("Cannot index into a value of type ({})", other);
+                self.report(&msg, index);
+                VarType::Var
+            }
+        }
+    }
+
+    /// The type of field `name` on a value of type `owner`.
+    fn field(&mut self, owner: &VarType, name: &str, index: usize) -> VarType {
+        match strip(owner) {
+            VarType::Struct(type_name) => match self.structs.get(&type_name).cloned() {
+                Some(decl) => match decl.fields.iter().find(|f| f.name == name) {
+                    Some(f) => f.ftype.clone(),
+                    None => {
+                        let msg = format!("'{}' has no field named '{}'", type_name, name);
+                        self.report(&msg, index);
+                        VarType::Var
+                    }
+                },
+                None if self.enums.contains_key(&type_name) => {
+                    self.report("Only modules, structs and enums have members", index);
+                    VarType::Var
+                }
+                None => VarType::Var,
+            },
+            VarType::Var => VarType::Var,
+            other => {
+                let msg = format!("A value of type ({}) has no members", other);
+                self.report(&msg, index);
+                VarType::Var
+            }
+        }
+    }
+
+    /// Checks the patterns of a `match` against its subject, and variant patterns against their
+    /// enum. A match on an enum without `_` must cover every variant; any other match without `_`
+    /// gets a warning, once its patterns are known to be fine.
+    fn check_patterns(
+        &mut self,
+        subject: &Expression,
+        subject_type: &VarType,
+        arms: &[MatchArm],
+        index: usize,
+    ) {
+        let reported = self.errors.len();
+        let mut on_enum: Option<&String> = None;
+        let mut covered: Vec<&String> = Vec::<&String>::new();
+        let mut last_index: usize = 0;
+        let mut has_wildcard = false;
+        for arm in arms {
+            for p in &arm.patterns {
+                let (enum_name, variant, names, idx) = match p {
+                    Pattern::Variant(enum_name, variant, names, idx) => {
+                        (enum_name, variant, names, *idx)
+                    }
+                    Pattern::Literal(lit) => {
+                        let lit_type = type_of_value(lit);
+                        if !comparable(subject_type, &lit_type) {
+                            let msg = format!(
+                                "Pattern of type ({}) can never match a value of type ({})",
+                                lit_type, subject_type
+                            );
+                            self.report(&msg, index_of(subject));
+                        }
+                        continue;
+                    }
+                    Pattern::Wildcard => {
+                        has_wildcard = true;
+                        continue;
+                    }
+                    _ => continue,
+                };
+                match on_enum {
+                    Some(e) if e != enum_name => {
+                        let msg = format!("Match mixes variants of '{}' and '{}'", e, enum_name);
+                        self.report(&msg, idx);
+                        continue;
+                    }
+                    Some(_) => {}
+                    None => {
+                        on_enum = Some(enum_name);
+                        if !self.enums.contains_key(enum_name) {
+                            self.report(&format!("Unknown enum '{}'", enum_name), idx);
+                        } else if !matches!(strip(subject_type), VarType::Var)
+                            && strip(subject_type) != VarType::Struct(enum_name.clone())
+                        {
+                            let msg = format!(
+                                "Variants of '{}' can never match a value of type ({})",
+                                enum_name, subject_type
+                            );
+                            self.report(&msg, idx);
+                        }
+                    }
+                }
+                let Some(decl) = self.enums.get(enum_name).cloned() else {
+                    continue;
+                };
+                match decl.variants.iter().find(|v| &v.name == variant) {
+                    None => {
+                        let msg = format!("'{}' has no variant named '{}'", enum_name, variant);
+                        self.report(&msg, idx);
+                    }
+                    Some(v) if !names.is_empty() && names.len() != v.payload.len() => {
+                        let msg = format!(
+                            "Pattern binds {} values but '{}.{}' has {}",
+                            names.len(),
+                            enum_name,
+                            variant,
+                            v.payload.len()
+                        );
+                        self.report(&msg, idx);
+                    }
+                    _ => {}
+                }
+                if !names.is_empty() && arm.patterns.len() > 1 {
+                    self.report("Patterns joined by '|' cannot bind values", idx);
+                }
+                covered.push(variant);
+                last_index = idx;
+            }
+        }
+
+        if self.errors.len() > reported || has_wildcard {
+            return;
+        }
+        match on_enum.and_then(|name| self.enums.get(name)) {
+            Some(decl) => {
+                let missing: Vec<String> = decl
+                    .variants
+                    .iter()
+                    .filter(|v| !covered.contains(&&v.name))
+                    .map(|v| format!("'{}.{}'", decl.name, v.name))
+                    .collect();
+                if !missing.is_empty() {
+                    let msg = format!(
+                        "Match on '{}' does not cover {}",
+                        decl.name,
+                        missing.join(", ")
+                    );
+                    self.report(&msg, last_index);
+                }
+            }
+            None => eprintln!(
+                "Warning: 'match' without a '_' arm at line {}",
+                get_line_from_index(self.lines, &index)
+            ),
+        }
+    }
+
+    /// The name of the enum `expr` refers to, when it is the plain name of a declared one.
+    fn enum_named(&self, expr: &Expression) -> Option<String> {
+        match expr {
+            Expression::Variable(name, _) if self.enums.contains_key(name) => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    fn variant_payload(&self, enum_name: &str, variant: &str) -> Option<Vec<VarType>> {
+        let decl = self.enums.get(enum_name)?;
+        decl.variants
+            .iter()
+            .find(|v| v.name == variant)
+            .map(|v| v.payload.clone())
+    }
+
+    fn declare(&mut self, name: &str, symbol: Symbol, index: usize) {
+        let scope = self.scopes.last_mut().unwrap();
+        if scope.contains_key(name) {
+            let msg = format!("Identifier '{}' is already declared in this scope", name);
+            self.report(&msg, index);
+            return;
+        }
+        scope.insert(name.to_owned(), symbol);
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn lookup_mut(&mut self, name: &str) -> Option<&mut Symbol> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+
+    /// Functions are checked once the block around them is done, so a name declared after a
+    /// function but before it is called is already known here.
+    fn undefined(&mut self, name: &str, index: usize) {
+        let msg = format!("Symbol '{}' does not exist!", name);
+        self.report(&msg, index);
+    }
+
+    fn report(&mut self, msg: &str, index: usize) {
+        let l = get_line_from_index(self.lines, &index);
+        let c = get_col(&index, self.lines);
+        self.errors
+            .push(format!("{} at line {}, column {}", msg, l, c));
+    }
+}
+
+impl Symbol {
+    fn plain(vtype: VarType) -> Symbol {
+        Symbol {
+            slot: vtype.clone(),
+            known: vtype,
+            fxn: None,
+            constant: false,
+            widens: false,
+        }
+    }
+    fn inferred(slot: VarType, known: VarType) -> Symbol {
+        Symbol {
+            slot,
+            known,
+            fxn: None,
+            constant: false,
+            widens: false,
+        }
+    }
+}
+
+// `null` on its own; anything nullable accepts it
+fn null_type() -> VarType {
+    VarType::Nullable(Box::new(VarType::Var))
+}
+
+fn is_null(t: &VarType) -> bool {
+    *t == null_type()
+}
+
+/// Drops `?`, since a nullable value is checked as the value it holds when it is not null.
+fn strip(t: &VarType) -> VarType {
+    match t {
+        _ if is_null(t) => VarType::Var,
+        VarType::Nullable(inner) => strip(inner),
+        other => other.clone(),
+    }
+}
+
+fn type_of_value(val: &Value) -> VarType {
+    match val {
+        Value::Null => null_type(),
+        other => other.infer_type(),
+    }
+}
+
+/// Whether a value of type `actual` may be stored where `declared` is expected.
+fn fits(declared: &VarType, actual: &VarType) -> bool {
+    match (declared, actual) {
+        (VarType::Var, _) => true,
+        (VarType::Nullable(_), a) if is_null(a) => true,
+        (_, a) if is_null(a) => false,
+        (_, VarType::Var) => true,
+        (d, VarType::Nullable(inner)) => fits(d, inner),
+        (VarType::Nullable(inner), a) => fits(inner, a),
+        (VarType::Num, VarType::Int) => true,
+        // items can be both read and stored through a shared container
+        (VarType::Array(d), VarType::Array(a)) => fits(d, a) && fits(a, d),
+        (VarType::Map(d), VarType::Map(a)) => fits(d, a) && fits(a, d),
+        (d, a) => d == a,
+    }
+}
+
+/// Whether `==` between the two types is defined. Only the basic types are known to clash.
+fn comparable(left: &VarType, right: &VarType) -> bool {
+    let (l, r) = (strip(left), strip(right));
+    let basic = |t: &VarType| matches!(t, VarType::Num | VarType::Int | VarType::Str | VarType::Bool);
+    let numeric = |t: &VarType| matches!(t, VarType::Num | VarType::Int);
+    if !basic(&l) || !basic(&r) {
+        return true;
+    }
+    l == r || (numeric(&l) && numeric(&r))
+}
+
+/// The element type of a collection holding both types.
+fn common(a: &VarType, b: &VarType) -> VarType {
+    match (a, b) {
+        _ if a == b => a.clone(),
+        (VarType::Num, VarType::Int) | (VarType::Int, VarType::Num) => VarType::Num,
+        _ => VarType::Var,
+    }
+}
+
+/// The slot a `var` gets, as `Value::infer_type` gives at runtime.
+fn slot_for(actual: &VarType) -> VarType {
+    match actual {
+        VarType::Array(_) => VarType::Array(Box::new(VarType::Var)),
+        VarType::Map(_) => VarType::Map(Box::new(VarType::Var)),
+        VarType::Nullable(_) => VarType::Var,
+        other => other.clone(),
+    }
+}
+
+fn operator_symbol(ttype: &TokenType) -> &'static str {
+    match ttype {
+        TokenType::Plus => "+",
+        TokenType::Minus => "-",
+        TokenType::Star => "*",
+        TokenType::Slash => "/",
+        TokenType::Mod => "%",
+        TokenType::Power => "**",
+        TokenType::BitAnd => "band",
+        TokenType::BitOr => "bor",
+        TokenType::BitXor => "bxor",
+        TokenType::ShiftLeft => "<<",
+        TokenType::ShiftRight => ">>",
+        TokenType::Tilde => "~",
+        TokenType::Bang => "!",
+        TokenType::Greater => ">",
+        TokenType::GreaterEquals => ">=",
+        TokenType::Lesser => "<",
+        TokenType::LesserEquals => "<=",
+        TokenType::Equality => "==",
+        TokenType::BangEquals => "!=",
+        TokenType::And => "&",
+        TokenType::Or => "|",
+        _ => "?",
+    }
+}
+
+/// Where an expression starts, for error messages.
+fn index_of(expr: &Expression) -> usize {
+    match expr {
+        Expression::SpecialSymbol(_, i)
+        | Expression::Literal(_, i)
+        | Expression::Variable(_, i)
+        | Expression::Assignment(_, _, i)
+        | Expression::Call(_, _, i)
+        | Expression::Reference(_, i)
+        | Expression::Query(_, i)
+        | Expression::ArrayLiteral(_, i)
+        | Expression::MapLiteral(_, i)
+        | Expression::Index(_, _, i)
+        | Expression::IndexAssignment(_, _, _, i)
+        | Expression::Get(_, _, i)
+        | Expression::SetField(_, _, _, i)
+        | Expression::StructLiteral(_, _, i)
+        | Expression::Convert(_, _, i) => *i,
+        Expression::Unary(sign, _) => sign.index,
+        Expression::Binary(_, sign, _) | Expression::CompoundAssignment(_, sign, _) => sign.index,
+        Expression::Group(inner) => index_of(inner),
+        Expression::Lambda(decl) => decl.index,
+    }
+}
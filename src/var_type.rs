@@ -0,0 +1,58 @@
+use crate::tokenizer::TokenType;
+use std::fmt;
+
+#[derive(Clone, PartialEq)]
+pub enum VarType {
+    Num,
+    Int,
+    Str,
+    Bool,
+    Fxn,
+    Var,
+    Array(Box<VarType>),
+    Map(Box<VarType>),
+    // a struct instance or enum variant of the type with this name
+    Struct(String),
+    Nullable(Box<VarType>),
+}
+
+impl VarType {
+    pub fn from_token(ttype: &TokenType) -> Option<VarType> {
+        match ttype {
+            TokenType::Num => Some(VarType::Num),
+            TokenType::Int => Some(VarType::Int),
+            TokenType::Str => Some(VarType::Str),
+            TokenType::Bool => Some(VarType::Bool),
+            TokenType::Fxn => Some(VarType::Fxn),
+            TokenType::Var => Some(VarType::Var),
+            _ => None,
+        }
+    }
+    /// The same type with every `int` in it turned into a `num`.
+    pub fn widened(&self) -> VarType {
+        match self {
+            VarType::Int => VarType::Num,
+            VarType::Array(elem) => VarType::Array(Box::new(elem.widened())),
+            VarType::Map(elem) => VarType::Map(Box::new(elem.widened())),
+            VarType::Nullable(inner) => VarType::Nullable(Box::new(inner.widened())),
+            other => other.clone(),
+        }
+    }
+}
+
+impl fmt::Display for VarType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VarType::Num => write!(f, "num"),
+            VarType::Int => write!(f, "int"),
+            VarType::Str => write!(f, "str"),
+            VarType::Bool => write!(f, "bool"),
+            VarType::Fxn => write!(f, "fxn"),
+            VarType::Var => write!(f, "var"),
+            VarType::Array(elem) => write!(f, "{}[]", elem),
+            VarType::Map(elem) => write!(f, "{}{{}}", elem),
+            VarType::Struct(name) => write!(f, "{}", name),
+            VarType::Nullable(inner) => write!(f, "{}?", inner),
+        }
+    }
+}
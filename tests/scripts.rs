@@ -0,0 +1,300 @@
+use std::process::{Command, Output};
+
+/// Runs `test/<name>.bdg` with the interpreter.
+fn run(name: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_badger-script"))
+        .args(["i", &format!("test/{}.bdg", name)])
+        .output()
+        .expect("could not start the interpreter")
+}
+
+/// Compiles `test/<name>.bdg` and returns the IR code.
+fn compile(name: &str) -> String {
+    let out = format!("{}/{}.ir", env!("CARGO_TARGET_TMPDIR"), name);
+    let output = Command::new(env!("CARGO_BIN_EXE_badger-script"))
+        .args(["c", &format!("test/{}.bdg", name), &out])
+        .output()
+        .expect("could not start the compiler");
+    assert!(output.status.success(), "{}", stdout(&output));
+    std::fs::read_to_string(out).expect("no IR code was written")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn var_from_int_literal_takes_nums() {
+    let output = run("var_widening");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "0.5\n");
+}
+
+#[test]
+fn widened_array_stays_shared() {
+    let output = run("shared_arrays");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "[0, 2, 3]\n");
+}
+
+#[test]
+fn int_array_cannot_alias_num_array() {
+    let output = run("shared_arrays_error");
+    assert!(!output.status.success());
+    assert!(stdout(&output).contains("Expression is not of expected type (num[]) at line 2"));
+}
+
+#[test]
+fn prime_check_script() {
+    let output = run("test");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "Number is prime : false\n");
+}
+
+#[test]
+fn catch_gets_its_own_thrown_value_and_line() {
+    let output = run("caught_errors");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "7\n7\n");
+}
+
+#[test]
+fn match_on_enum_declared_below() {
+    let output = run("enum_declared_later");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "green\n");
+}
+
+#[test]
+fn match_missing_variant_is_one_error() {
+    let output = run("enum_match_missing");
+    assert!(!output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "Error: Match on 'Color' does not cover 'Color.Blue' at line 4, column 9\n\
+         Type checking failed with 1 errors\n"
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).is_empty());
+}
+
+#[test]
+fn unknown_name_in_fxn_body_is_reported() {
+    let output = run("unknown_in_fxn");
+    assert!(!output.status.success());
+    assert!(stdout(&output).contains("Symbol 'factr' does not exist! at line 2, column 16"));
+}
+
+#[test]
+fn fxn_sees_names_declared_after_it() {
+    let output = run("declared_after_fxn");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "6\n");
+}
+
+#[test]
+fn loop_catch_and_match_names_shadow_consts() {
+    let output = run("const_shadowed");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "1\n2\n3\n4\n10\n");
+
+    // only the last `println(n)` sees the const and gets its value in place
+    let ir = compile("const_shadowed");
+    assert_eq!(ir.matches("arg 10").count(), 1, "{}", ir);
+}
+
+#[test]
+fn hex_escape_takes_no_sign() {
+    let output = run("hex_escape_sign");
+    assert!(!output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "Error: Expected two hex digits after '\\x' at line 1, column 11\n"
+    );
+}
+
+#[test]
+fn compound_assignment_through_reference() {
+    let output = run("compound_through_reference");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "4\n");
+}
+
+#[test]
+fn increments_are_statements() {
+    let output = run("step_statements");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "[1, -1] 4\n");
+}
+
+#[test]
+fn increment_has_no_value() {
+    let output = run("step_as_value");
+    assert!(stdout(&output).contains(
+        "'++' and '--' go after a variable, an item or a field, as a statement of their own at line 2"
+    ));
+}
+
+#[test]
+fn early_exits_run_finally() {
+    let output = run("try_exits");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "left 0\nleft 1\nleft 2\nfin\n1\n");
+
+    // the compiled `return` pops the handler and only returns after `finally`
+    let ir = compile("try_exits");
+    let lines: Vec<&str> = ir.lines().collect();
+    let at = |start: &str| lines.iter().position(|l| l.starts_with(start)).expect(&ir);
+    assert!(at("endfinally") < at("ret _ret"), "{}", ir);
+    assert_eq!(lines[at("new _ret") - 1], "endtry", "{}", ir);
+    assert!(!lines.contains(&"ret 1"), "{}", ir);
+}
+
+#[test]
+fn map_loop_goes_through_keys() {
+    let output = run("map_loop");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "ann 31\nbo 4\n");
+
+    // the compiled loop indexes the keys, not the map itself
+    let ir = compile("map_loop");
+    assert!(ir.contains("_each6 = items t0\n"), "{}", ir);
+    assert!(ir.contains("k = _each6[_at6]\n"), "{}", ir);
+}
+
+#[test]
+fn runaway_recursion_is_a_runtime_error() {
+    let output = run("deep_recursion");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(
+        stdout(&output),
+        "900\nCalls nest deeper than 1000 in 'down' 5\n3\n"
+    );
+}
+
+#[test]
+fn ir_strings_stay_on_one_line() {
+    let ir = compile("ir_strings");
+    assert!(ir.contains("greeting = 'it\\'s \"here\"\\\\'\n"), "{}", ir);
+    assert!(ir.contains("arg 'it\\'s\\nhere'\n"), "{}", ir);
+    assert!(ir.contains("arg 'two\\nlines'\n"), "{}", ir);
+    assert!(ir.contains("t0 = _match8 eq 'a\\'b'\n"), "{}", ir);
+    assert_eq!(ir.lines().count(), 22, "{}", ir);
+}
+
+#[test]
+fn structs_cannot_be_exported() {
+    let output = run("export_struct");
+    assert!(!output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "Error: Structs cannot be exported at line 1\nError!\n\
+         Could not parse module 'test/lib/shapes.bdg': Parsing failed with errors at line 1\n"
+    );
+}
+
+#[test]
+fn struct_values_cross_modules() {
+    let output = run("struct_from_module");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "7\n");
+}
+
+#[test]
+fn enums_cannot_be_exported() {
+    let output = run("export_enum");
+    assert!(!output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "Error: Enums cannot be exported at line 1\nError!\n\
+         Could not parse module 'test/lib/colors.bdg': Parsing failed with errors at line 1\n"
+    );
+}
+
+#[test]
+fn enum_values_cross_modules() {
+    let output = run("enum_from_module");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "State.Off\n");
+}
+
+#[test]
+fn variants_must_match_the_subject_type() {
+    let output = run("enum_match_wrong_type");
+    assert!(!output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "Error: Variants of 'D' can never match a value of type (C) at line 4, column 5\n\
+         Error: Variants of 'C' can never match a value of type (int) at line 9, column 5\n\
+         Type checking failed with 2 errors\n"
+    );
+}
+
+#[test]
+fn readme_reference_examples_run() {
+    let output = run("readme_references");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "1\n[2, 2]\n");
+}
+
+#[test]
+fn import_cycle_is_reported() {
+    let output = run("import_cycle");
+    assert!(!output.status.success());
+    assert!(stdout(&output)
+        .contains("Import cycle detected: cycle_a.bdg -> cycle_b.bdg -> cycle_a.bdg at line 1"));
+}
+
+#[test]
+fn module_runs_once_and_keeps_its_state() {
+    let output = run("module_exports");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "counter loaded\n1\n3\n4\n");
+}
+
+#[test]
+fn unexported_names_stay_in_their_module() {
+    let output = run("module_hidden");
+    assert!(!output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "counter loaded\nError!\nModule 'counter' does not export 'count' at line 2\n"
+    );
+}
+
+#[test]
+fn type_checker_reports_every_error_before_running() {
+    let output = run("type_errors");
+    assert!(!output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "Error: Expression is not of expected type (int) at line 2, column 6\n\
+         Error: Expression is not of expected type (str) at line 3, column 6\n\
+         Error: Argument 'x' of function 'half' is not of expected type (num) at line 7, column 5\n\
+         Error: Function 'half' expects 1 arguments but got 2 at line 8, column 5\n\
+         Error: Operator '<' is not defined for (int) and (str) at line 9, column 12\n\
+         Error: Cannot assign a value of type (str) to 'v' of type (int) at line 11, column 1\n\
+         Error: Cannot assign to constant 'k' at line 13, column 1\n\
+         Error: Symbol 'undeclared' does not exist! at line 14, column 1\n\
+         Error: Function 'half' must return a value of type (num) at line 5, column 12\n\
+         Type checking failed with 9 errors\n"
+    );
+}
+
+#[test]
+fn compound_assignment_on_every_target() {
+    let output = run("compound_assignment");
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert_eq!(stdout(&output), "3\n4.5\n[1, 20]\n{'a': 1.5}\n3\nabc\n4\n");
+}
+
+#[test]
+fn compound_assignment_keeps_int_vars_int() {
+    let output = run("compound_assignment_error");
+    assert!(!output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "Error: Cannot assign a value of type (num) to 'n' of type (int) at line 2, column 1\n\
+         Error: Cannot assign a value of type (num) to 'n' of type (int) at line 3, column 1\n\
+         Type checking failed with 2 errors\n"
+    );
+}